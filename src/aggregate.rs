@@ -0,0 +1,255 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use serde_json::{Value as JsonValue, Map};
+use crate::codec::decode_doc;
+use crate::config::DbConfig;
+use crate::errors::OsunbitDBError;
+use crate::filter::{compare, Filter};
+use crate::scan::{collection_range, doc_id, Direction, Reader, MAX_SCAN_LIMIT};
+use crate::utils::get_deep;
+
+/// One aggregate function over a dot-notation path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateOp {
+    Count,
+    Sum(String),
+    Avg(String),
+    Min(String),
+    Max(String),
+}
+
+/// Aggregation query, evaluated while streaming over the collection so memory
+/// only grows with the number of groups.
+///
+/// `Aggregation::new().count("n").sum("total", "amount").group_by("type")`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aggregation {
+    pub ops: Vec<(String, AggregateOp)>,
+    pub group_by: Option<String>,
+    pub filter: Option<Filter>,
+}
+
+impl Aggregation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn count(self, alias: &str) -> Self {
+        self.op(alias, AggregateOp::Count)
+    }
+
+    pub fn sum(self, alias: &str, path: &str) -> Self {
+        self.op(alias, AggregateOp::Sum(path.to_string()))
+    }
+
+    pub fn avg(self, alias: &str, path: &str) -> Self {
+        self.op(alias, AggregateOp::Avg(path.to_string()))
+    }
+
+    pub fn min(self, alias: &str, path: &str) -> Self {
+        self.op(alias, AggregateOp::Min(path.to_string()))
+    }
+
+    pub fn max(self, alias: &str, path: &str) -> Self {
+        self.op(alias, AggregateOp::Max(path.to_string()))
+    }
+
+    pub fn op(mut self, alias: &str, op: AggregateOp) -> Self {
+        self.ops.push((alias.to_string(), op));
+        self
+    }
+
+    pub fn group_by(mut self, path: &str) -> Self {
+        self.group_by = Some(path.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+/// Aggregates of one group. `key` is the `group_by` value (`Null` when the
+/// document has no such field, or when there is no `group_by`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateGroup {
+    pub key: JsonValue,
+    pub values: Map<String, JsonValue>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AggregateResult {
+    pub groups: Vec<AggregateGroup>,
+}
+
+impl AggregateResult {
+    /// Values of the single group of an aggregation without `group_by`.
+    pub fn values(&self) -> Option<&Map<String, JsonValue>> {
+        self.groups.first().map(|g| &g.values)
+    }
+
+    pub fn group(&self, key: &JsonValue) -> Option<&Map<String, JsonValue>> {
+        self.groups.iter().find(|g| &g.key == key).map(|g| &g.values)
+    }
+
+    /// `[{"key": ..., "<alias>": ...}, ...]`
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Array(
+            self.groups
+                .iter()
+                .map(|g| {
+                    let mut obj = g.values.clone();
+                    obj.insert("key".to_string(), g.key.clone());
+                    JsonValue::Object(obj)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Acc {
+    Count(u64),
+    /// Integer sum until a float shows up.
+    Sum(Option<i64>, f64, bool),
+    Avg(f64, u64),
+    Min(Option<JsonValue>),
+    Max(Option<JsonValue>),
+}
+
+impl Acc {
+    fn new(op: &AggregateOp) -> Self {
+        match op {
+            AggregateOp::Count => Acc::Count(0),
+            AggregateOp::Sum(_) => Acc::Sum(Some(0), 0.0, false),
+            AggregateOp::Avg(_) => Acc::Avg(0.0, 0),
+            AggregateOp::Min(_) => Acc::Min(None),
+            AggregateOp::Max(_) => Acc::Max(None),
+        }
+    }
+
+    fn add(&mut self, op: &AggregateOp, doc: &Map<String, JsonValue>) {
+        let field = match op {
+            AggregateOp::Count => None,
+            AggregateOp::Sum(p) | AggregateOp::Avg(p) | AggregateOp::Min(p) | AggregateOp::Max(p) => get_deep(doc, p),
+        };
+
+        match self {
+            Acc::Count(n) => *n += 1,
+            Acc::Sum(int, float, seen) => {
+                if let Some(v) = field.filter(|v| v.is_number()) {
+                    *seen = true;
+                    *float += v.as_f64().unwrap_or(0.0);
+                    *int = match (*int, v.as_i64()) {
+                        (Some(a), Some(b)) => a.checked_add(b),
+                        _ => None,
+                    };
+                }
+            }
+            Acc::Avg(sum, n) => {
+                if let Some(v) = field.and_then(|v| v.as_f64()) {
+                    *sum += v;
+                    *n += 1;
+                }
+            }
+            Acc::Min(cur) => Self::keep(cur, field, Ordering::Less),
+            Acc::Max(cur) => Self::keep(cur, field, Ordering::Greater),
+        }
+    }
+
+    /// Replace `cur` with `field` when it compares as `want`; mixed types are skipped.
+    fn keep(cur: &mut Option<JsonValue>, field: Option<&JsonValue>, want: Ordering) {
+        let Some(v) = field.filter(|v| !v.is_null()) else {
+            return;
+        };
+        match cur {
+            None => *cur = Some(v.clone()),
+            Some(c) if compare(v, c) == Some(want) => *cur = Some(v.clone()),
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> JsonValue {
+        match self {
+            Acc::Count(n) => JsonValue::from(n),
+            Acc::Sum(_, _, false) => JsonValue::from(0),
+            Acc::Sum(Some(int), _, true) => JsonValue::from(int),
+            Acc::Sum(None, float, true) => JsonValue::from(float),
+            Acc::Avg(_, 0) => JsonValue::Null,
+            Acc::Avg(sum, n) => JsonValue::from(sum / n as f64),
+            Acc::Min(v) | Acc::Max(v) => v.unwrap_or(JsonValue::Null),
+        }
+    }
+}
+
+pub(crate) async fn aggregate(
+    reader: &mut Reader<'_>,
+    config: &DbConfig,
+    collection: &str,
+    agg: &Aggregation,
+) -> Result<AggregateResult, OsunbitDBError> {
+    config.limits.check_collection(collection)?;
+    if agg.ops.is_empty() {
+        return Err(OsunbitDBError::invalid_argument(
+            "aggregation needs at least one function".to_string(),
+        ));
+    }
+
+    // Groups keyed by their serialized key so any JSON value can group
+    let mut groups: BTreeMap<String, (JsonValue, Vec<Acc>)> = BTreeMap::new();
+    let empty = Map::new();
+    let range = collection_range(collection, Bound::Unbounded, Bound::Unbounded);
+
+    reader
+        .walk(range, Direction::Ascending, false, MAX_SCAN_LIMIT, |key, value| {
+            // Subcollection documents are not part of this collection
+            let id = doc_id(collection, &key);
+            if id.contains(':') {
+                return Ok(true);
+            }
+            let doc: JsonValue = match value {
+                Some(bytes) => decode_doc(config, collection, &id, &bytes).unwrap_or(JsonValue::Null),
+                None => return Ok(true),
+            };
+            if !agg.filter.as_ref().is_none_or(|f| f.matches(&doc)) {
+                return Ok(true);
+            }
+            let obj = doc.as_object().unwrap_or(&empty);
+
+            let key = agg
+                .group_by
+                .as_ref()
+                .and_then(|p| get_deep(obj, p).cloned())
+                .unwrap_or(JsonValue::Null);
+            let (_, accs) = groups
+                .entry(key.to_string())
+                .or_insert_with(|| (key, agg.ops.iter().map(|(_, op)| Acc::new(op)).collect()));
+
+            for (acc, (_, op)) in accs.iter_mut().zip(&agg.ops) {
+                acc.add(op, obj);
+            }
+            Ok(true)
+        })
+        .await?;
+
+    // Without group_by an empty collection still yields one row of zeros/nulls
+    if groups.is_empty() && agg.group_by.is_none() {
+        groups.insert(String::new(), (JsonValue::Null, agg.ops.iter().map(|(_, op)| Acc::new(op)).collect()));
+    }
+
+    let groups = groups
+        .into_values()
+        .map(|(key, accs)| AggregateGroup {
+            key,
+            values: agg
+                .ops
+                .iter()
+                .zip(accs)
+                .map(|((alias, _), acc)| (alias.clone(), acc.finish()))
+                .collect(),
+        })
+        .collect();
+    Ok(AggregateResult { groups })
+}
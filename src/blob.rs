@@ -0,0 +1,639 @@
+use std::io;
+use std::ops::Bound;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tikv_rust::{BoundRange, Key, Snapshot};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use crate::config::DbConfig;
+use crate::encryption;
+use crate::errors::{OsunbitDBError, ResultExt};
+use crate::scan::{Direction, Reader, MAX_SCAN_LIMIT};
+use crate::transaction::TransactionHandle;
+use crate::utils::meta_key;
+use crate::validation::Limits;
+
+/// Metadata of a blob stored under a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobInfo {
+    pub name: String,
+    pub content_type: String,
+    /// Length in bytes.
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the content, checked on every full read.
+    pub sha256: String,
+}
+
+/// A blob read in one piece with `get_blob`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    pub info: BlobInfo,
+    pub data: Vec<u8>,
+}
+
+/// Value at the blob's metadata key.
+#[derive(Serialize, Deserialize)]
+struct StoredBlob {
+    content_type: String,
+    size: u64,
+    sha256: String,
+    chunks: u32,
+    /// Chunks are sealed with the collection's envelope encryption.
+    encrypted: bool,
+}
+
+impl StoredBlob {
+    fn info(&self, name: &str) -> BlobInfo {
+        BlobInfo {
+            name: name.to_string(),
+            content_type: self.content_type.clone(),
+            size: self.size,
+            sha256: self.sha256.clone(),
+        }
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self, OsunbitDBError> {
+        serde_json::from_slice(bytes).map_err(|e| OsunbitDBError::corruption(format!("invalid blob metadata: {}", e)))
+    }
+}
+
+/// Where one blob lives. Blob keys sit under the meta prefix, out of every
+/// collection range, with NUL separating the document key from the blob
+/// name so blobs of `users:u1` never mix with those of `users:u1:...`.
+#[derive(Debug, Clone)]
+struct BlobTarget {
+    doc: String,
+    name: String,
+}
+
+impl BlobTarget {
+    fn new(limits: &Limits, collection: &str, id: &str, name: &str) -> Result<Self, OsunbitDBError> {
+        limits.check_key(collection, id)?;
+        if name.is_empty() || name.contains('\u{0}') {
+            return Err(OsunbitDBError::invalid_argument(format!("invalid blob name {:?}", name)));
+        }
+        Ok(BlobTarget { doc: format!("{}:{}", collection, id), name: name.to_string() })
+    }
+
+    fn meta_key(&self) -> Key {
+        meta_key("blob", &format!("{}\u{0}{}", self.doc, self.name))
+    }
+
+    fn marker_key(&self) -> Key {
+        meta_key("blobs", &self.doc)
+    }
+
+    fn chunk_key(&self, index: u32) -> Key {
+        meta_key("blobchunk", &format!("{}\u{0}{}\u{0}{:08}", self.doc, self.name, index))
+    }
+}
+
+/// Set on a document's first blob, so deleting documents without blobs
+/// skips the blob range scan. It may outlive the blobs it marks.
+pub(crate) fn marker_key(collection: &str, id: &str) -> Key {
+    meta_key("blobs", &format!("{}:{}", collection, id))
+}
+
+/// Metadata keys of every blob of a document.
+fn blob_range(collection: &str, id: &str) -> BoundRange {
+    BoundRange {
+        from: Bound::Included(meta_key("blob", &format!("{}:{}\u{0}", collection, id))),
+        to: Bound::Excluded(meta_key("blob", &format!("{}:{}\u{1}", collection, id))),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn read_meta(reader: &mut Reader<'_>, target: &BlobTarget) -> Result<Option<StoredBlob>, OsunbitDBError> {
+    reader.get(target.meta_key()).await?.map(|v| StoredBlob::parse(&v)).transpose()
+}
+
+/// Chunk `index` as written, decrypted when the blob is encrypted.
+async fn read_chunk(
+    reader: &mut Reader<'_>,
+    config: &DbConfig,
+    target: &BlobTarget,
+    stored: &StoredBlob,
+    index: u32,
+) -> Result<Vec<u8>, OsunbitDBError> {
+    let bytes = reader.get(target.chunk_key(index)).await?.ok_or_else(|| {
+        OsunbitDBError::corruption(format!("chunk {} of blob {} is missing", index, target.name))
+    })?;
+    if stored.encrypted {
+        encryption::open(config.key_provider.as_deref(), (&target.chunk_key(index)).into(), &bytes)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// The whole blob `name` of `collection/id`, checked against its SHA-256.
+pub(crate) async fn get_blob(
+    reader: &mut Reader<'_>,
+    config: &DbConfig,
+    collection: &str,
+    id: &str,
+    name: &str,
+) -> Result<Option<Blob>, OsunbitDBError> {
+    let target = BlobTarget::new(&config.limits, collection, id, name)?;
+    let Some(stored) = read_meta(reader, &target).await? else {
+        return Ok(None);
+    };
+    let mut data = Vec::with_capacity(stored.size as usize);
+    for i in 0..stored.chunks {
+        data.extend(read_chunk(reader, config, &target, &stored, i).await?);
+    }
+    if hex(&Sha256::digest(&data)) != stored.sha256 {
+        return Err(OsunbitDBError::corruption(format!("blob {} does not match its SHA-256", name)));
+    }
+    Ok(Some(Blob { info: stored.info(name), data }))
+}
+
+/// Metadata of every blob of `collection/id`, by name.
+pub(crate) async fn list_blobs(
+    reader: &mut Reader<'_>,
+    config: &DbConfig,
+    collection: &str,
+    id: &str,
+) -> Result<Vec<BlobInfo>, OsunbitDBError> {
+    config.limits.check_key(collection, id)?;
+    let prefix_len = Vec::<u8>::from(meta_key("blob", &format!("{}:{}\u{0}", collection, id))).len();
+    let mut entries = Vec::new();
+    reader
+        .walk(blob_range(collection, id), Direction::Ascending, false, MAX_SCAN_LIMIT, |key, value| {
+            let key: &[u8] = (&key).into();
+            let name = String::from_utf8_lossy(&key[prefix_len..]).to_string();
+            entries.push((name, value.unwrap_or_default()));
+            Ok(true)
+        })
+        .await?;
+    entries.into_iter().map(|(name, value)| Ok(StoredBlob::parse(&value)?.info(&name))).collect()
+}
+
+impl TransactionHandle {
+    /// Store `data` as blob `name` of the document `collection/id`, replacing
+    /// any blob of that name. The document must exist; chunks and metadata
+    /// commit with the rest of the transaction.
+    pub async fn put_blob(
+        &mut self,
+        collection: &str,
+        id: &str,
+        name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Result<BlobInfo, OsunbitDBError> {
+        async {
+            let target = BlobTarget::new(&self.config.limits, collection, id, name)?;
+            let chunk_size = self.config.chunk_size();
+            let mut count = 0;
+            for (i, part) in data.chunks(chunk_size).enumerate() {
+                self.put_blob_chunk(collection, &target, i as u32, part).await?;
+                count += 1;
+            }
+            self.put_blob_meta(collection, &target, content_type, data.len() as u64, hex(&Sha256::digest(data)), count).await
+        }
+        .await
+        .with_context("put_blob", collection, Some(id))
+    }
+
+    /// Read blob `name` of `collection/id` in one piece.
+    pub async fn get_blob(&mut self, collection: &str, id: &str, name: &str) -> Result<Option<Blob>, OsunbitDBError> {
+        get_blob(&mut Reader::Tx(&mut self.tx), &self.config, collection, id, name).await
+            .with_context("get_blob", collection, Some(id))
+    }
+
+    /// Metadata of every blob of `collection/id`.
+    pub async fn list_blobs(&mut self, collection: &str, id: &str) -> Result<Vec<BlobInfo>, OsunbitDBError> {
+        list_blobs(&mut Reader::Tx(&mut self.tx), &self.config, collection, id).await
+            .with_context("list_blobs", collection, Some(id))
+    }
+
+    /// Remove blob `name` of `collection/id`. Returns whether it existed.
+    pub async fn delete_blob(&mut self, collection: &str, id: &str, name: &str) -> Result<bool, OsunbitDBError> {
+        async {
+            let target = BlobTarget::new(&self.config.limits, collection, id, name)?;
+            let Some(stored) = read_meta(&mut Reader::Tx(&mut self.tx), &target).await? else {
+                return Ok(false);
+            };
+            for i in 0..stored.chunks {
+                self.tx.delete(target.chunk_key(i)).await?;
+            }
+            self.tx.delete(target.meta_key()).await?;
+            Ok(true)
+        }
+        .await
+        .with_context("delete_blob", collection, Some(id))
+    }
+
+    /// Remove every blob of `collection/id` and its marker, used when the
+    /// document is deleted.
+    pub(crate) async fn delete_blobs(&mut self, collection: &str, id: &str) -> Result<(), OsunbitDBError> {
+        for info in self.list_blobs(collection, id).await? {
+            self.delete_blob(collection, id, &info.name).await?;
+        }
+        self.tx.delete(marker_key(collection, id)).await?;
+        Ok(())
+    }
+
+    /// Re-seal the blobs of `collection/id` whose chunks do not match the
+    /// collection's encryption: another cipher or key id, or encrypted while
+    /// encryption is off (and the reverse). Used by `OsunbitDB::rewrite`;
+    /// returns how many blobs were rewritten. A blob that no longer opens is
+    /// left alone, like an undecodable document.
+    pub(crate) async fn rewrite_blobs(&mut self, collection: &str, id: &str) -> Result<u64, OsunbitDBError> {
+        let cipher = self.config.collection(collection).and_then(|c| c.encryption);
+        let current_key = self.config.key_provider.as_deref().map(|p| p.current_key_id());
+        let mut rewritten = 0;
+        for info in self.list_blobs(collection, id).await? {
+            let target = BlobTarget::new(&self.config.limits, collection, id, &info.name)?;
+            let Some(stored) = read_meta(&mut Reader::Tx(&mut self.tx), &target).await? else { continue };
+            let current = match (cipher, stored.encrypted, stored.chunks) {
+                (None, false, _) => true,
+                (Some(_), true, 0) => true,
+                (Some(cipher), true, _) => self.tx.get(target.chunk_key(0)).await?.is_some_and(|first| {
+                    encryption::cipher(&first) == Some(cipher) && encryption::key_id(&first) == current_key.as_deref()
+                }),
+                _ => false,
+            };
+            if current {
+                continue;
+            }
+            let mut chunks = Vec::with_capacity(stored.chunks as usize);
+            for i in 0..stored.chunks {
+                match read_chunk(&mut Reader::Tx(&mut self.tx), &self.config, &target, &stored, i).await {
+                    Ok(chunk) => chunks.push(chunk),
+                    Err(_) => break,
+                }
+            }
+            if chunks.len() < stored.chunks as usize {
+                continue;
+            }
+            for (i, chunk) in chunks.iter().enumerate() {
+                self.put_blob_chunk(collection, &target, i as u32, chunk).await?;
+            }
+            self.put_blob_meta(collection, &target, &stored.content_type, stored.size, stored.sha256, stored.chunks).await?;
+            rewritten += 1;
+        }
+        Ok(rewritten)
+    }
+
+    /// Stream blob `name` of `collection/id` into this transaction through
+    /// `AsyncWrite`, one chunk in memory at a time. Get the transaction
+    /// back with `BlobWriter::finish`.
+    pub fn blob_writer(self, collection: &str, id: &str, name: &str, content_type: &str) -> Result<BlobWriter, OsunbitDBError> {
+        let target = BlobTarget::new(&self.config.limits, collection, id, name).with_context("blob_writer", collection, Some(id))?;
+        let chunk_size = self.config.chunk_size();
+        let upload = Upload {
+            tx: self,
+            collection: collection.to_string(),
+            target,
+            content_type: content_type.to_string(),
+            buffer: Vec::new(),
+            hasher: Sha256::new(),
+            size: 0,
+            chunks: 0,
+            error: None,
+            info: None,
+        };
+        Ok(BlobWriter {
+            state: WriteState::Idle(Box::new(upload)),
+            chunk_size,
+            collection: collection.to_string(),
+            id: id.to_string(),
+        })
+    }
+
+    async fn put_blob_chunk(&mut self, collection: &str, target: &BlobTarget, index: u32, data: &[u8]) -> Result<(), OsunbitDBError> {
+        let bytes = match self.config.collection(collection).and_then(|c| c.encryption) {
+            Some(cipher) => {
+                let provider = self.config.key_provider.as_deref().ok_or_else(|| {
+                    OsunbitDBError::encryption(format!("{} is encrypted but no key provider is set", collection))
+                })?;
+                encryption::seal(cipher, provider, (&target.chunk_key(index)).into(), data)?
+            }
+            None => data.to_vec(),
+        };
+        self.tx.put(target.chunk_key(index), bytes).await?;
+        Ok(())
+    }
+
+    /// Write the metadata once all `chunks` are stored. Locks the parent
+    /// document, so a concurrent delete cannot leave the blob behind, and
+    /// drops chunks of a previous, longer blob of the same name.
+    async fn put_blob_meta(
+        &mut self,
+        collection: &str,
+        target: &BlobTarget,
+        content_type: &str,
+        size: u64,
+        sha256: String,
+        chunks: u32,
+    ) -> Result<BlobInfo, OsunbitDBError> {
+        if self.tx.get_for_update(Key::from(target.doc.clone())).await?.is_none() {
+            return Err(OsunbitDBError::NotFound {
+                collection: collection.to_string(),
+                id: target.doc[collection.len() + 1..].to_string(),
+                context: None,
+            });
+        }
+        if let Some(old) = read_meta(&mut Reader::Tx(&mut self.tx), target).await? {
+            for i in chunks..old.chunks {
+                self.tx.delete(target.chunk_key(i)).await?;
+            }
+        }
+        self.tx.put(target.marker_key(), vec![1]).await?;
+        let encrypted = self.config.collection(collection).is_some_and(|c| c.encryption.is_some());
+        let stored = StoredBlob { content_type: content_type.to_string(), size, sha256, chunks, encrypted };
+        self.tx.put(target.meta_key(), serde_json::to_vec(&stored)?).await?;
+        Ok(stored.info(&target.name))
+    }
+}
+
+/// State of a streaming upload, moved into each pending write.
+struct Upload {
+    tx: TransactionHandle,
+    collection: String,
+    target: BlobTarget,
+    content_type: String,
+    buffer: Vec<u8>,
+    hasher: Sha256,
+    size: u64,
+    chunks: u32,
+    /// First failed write; the writer refuses further input after it.
+    error: Option<OsunbitDBError>,
+    /// Set once the metadata is written.
+    info: Option<BlobInfo>,
+}
+
+impl Upload {
+    async fn push(&mut self, data: Vec<u8>) -> Result<(), OsunbitDBError> {
+        self.hasher.update(&data);
+        self.size += data.len() as u64;
+        self.tx.put_blob_chunk(&self.collection, &self.target, self.chunks, &data).await?;
+        self.chunks += 1;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), OsunbitDBError> {
+        if !self.buffer.is_empty() {
+            let rest = std::mem::take(&mut self.buffer);
+            self.push(rest).await?;
+        }
+        let sha256 = hex(&self.hasher.clone().finalize());
+        let info = self
+            .tx
+            .put_blob_meta(&self.collection, &self.target, &self.content_type, self.size, sha256, self.chunks)
+            .await?;
+        self.info = Some(info);
+        Ok(())
+    }
+}
+
+type Step = BoxFuture<'static, (Box<Upload>, Result<(), OsunbitDBError>)>;
+
+enum WriteState {
+    Idle(Box<Upload>),
+    Busy(Step),
+    /// Only while switching between the two.
+    Empty,
+}
+
+/// `AsyncWrite` into a blob, from `TransactionHandle::blob_writer` or
+/// `OsunbitDB::blob_writer`. Bytes go out one chunk at a time; shutting the
+/// writer down stores the last chunk and the metadata. Nothing is visible
+/// until the transaction commits.
+pub struct BlobWriter {
+    state: WriteState,
+    chunk_size: usize,
+    // For error context, the upload is out of reach while a step runs
+    collection: String,
+    id: String,
+}
+
+impl BlobWriter {
+    fn take(&mut self) -> Box<Upload> {
+        match std::mem::replace(&mut self.state, WriteState::Empty) {
+            WriteState::Idle(upload) => upload,
+            _ => unreachable!("blob writer step started while busy"),
+        }
+    }
+
+    fn start_push(&mut self, data: Vec<u8>) {
+        let mut upload = self.take();
+        self.state = WriteState::Busy(Box::pin(async move {
+            let res = upload.push(data).await;
+            (upload, res)
+        }));
+    }
+
+    fn start_close(&mut self) {
+        let mut upload = self.take();
+        self.state = WriteState::Busy(Box::pin(async move {
+            let res = upload.close().await;
+            (upload, res)
+        }));
+    }
+
+    /// Finish the pending write, if any, and fail once a write has failed.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&mut Upload>> {
+        if let WriteState::Busy(step) = &mut self.state {
+            let (mut upload, res) = ready!(step.as_mut().poll(cx));
+            if let Err(e) = res {
+                upload.error = Some(e);
+            }
+            self.state = WriteState::Idle(upload);
+        }
+        match &mut self.state {
+            WriteState::Idle(upload) => match &upload.error {
+                Some(e) => Poll::Ready(Err(io::Error::other(e.to_string()))),
+                None => Poll::Ready(Ok(upload)),
+            },
+            _ => unreachable!("blob writer lost its upload"),
+        }
+    }
+
+    /// Store what is left and the metadata (unless shut down already) and
+    /// return the transaction, uncommitted, with the blob's metadata. On
+    /// error the transaction is rolled back.
+    pub async fn finish(self) -> Result<(TransactionHandle, BlobInfo), OsunbitDBError> {
+        let mut upload = match self.state {
+            WriteState::Idle(upload) => upload,
+            WriteState::Busy(step) => {
+                let (mut upload, res) = step.await;
+                if let Err(e) = res {
+                    upload.error.get_or_insert(e);
+                }
+                upload
+            }
+            WriteState::Empty => unreachable!("blob writer lost its upload"),
+        };
+        if upload.error.is_none() && upload.info.is_none() {
+            if let Err(e) = upload.close().await {
+                upload.error = Some(e);
+            }
+        }
+        let Upload { tx, error, info, .. } = *upload;
+        match (error, info) {
+            (None, Some(info)) => Ok((tx, info)),
+            (error, _) => {
+                let _ = tx.rollback().await;
+                let error = error.unwrap_or_else(|| OsunbitDBError::invalid_argument("blob upload did not complete".to_string()));
+                Err(error.with_context("blob_writer", &self.collection, Some(&self.id)))
+            }
+        }
+    }
+
+    /// `finish`, then commit the transaction.
+    pub async fn commit(self) -> Result<BlobInfo, OsunbitDBError> {
+        let (collection, id) = (self.collection.clone(), self.id.clone());
+        let (tx, info) = self.finish().await?;
+        tx.finish(Ok(info)).await.with_context("blob_writer", &collection, Some(&id))
+    }
+}
+
+impl AsyncWrite for BlobWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let chunk_size = this.chunk_size;
+        loop {
+            let upload = ready!(this.poll_idle(cx))?;
+            if upload.info.is_some() {
+                return Poll::Ready(Err(io::Error::other("blob writer is already shut down")));
+            }
+            if upload.buffer.len() < chunk_size {
+                let n = buf.len().min(chunk_size - upload.buffer.len());
+                upload.buffer.extend_from_slice(&buf[..n]);
+                return Poll::Ready(Ok(n));
+            }
+            let full: Vec<u8> = upload.buffer.drain(..chunk_size).collect();
+            this.start_push(full);
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_idle(cx).map_ok(|_| ())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let upload = ready!(this.poll_idle(cx))?;
+            if upload.info.is_some() {
+                return Poll::Ready(Ok(()));
+            }
+            this.start_close();
+        }
+    }
+}
+
+/// State of a streaming read, moved into each pending chunk fetch.
+struct Download {
+    snapshot: Snapshot,
+    config: Arc<DbConfig>,
+    target: BlobTarget,
+    stored: StoredBlob,
+    next: u32,
+    current: Vec<u8>,
+    pos: usize,
+    hasher: Sha256,
+}
+
+type Fetch = BoxFuture<'static, (Box<Download>, Result<Vec<u8>, OsunbitDBError>)>;
+
+enum ReadState {
+    Idle(Box<Download>),
+    Busy(Fetch),
+    Empty,
+}
+
+/// `AsyncRead` over a blob from `OsunbitDB::blob_reader`, reading one chunk
+/// at a time from a single snapshot. The SHA-256 is checked at the end; a
+/// mismatch fails the last read with `InvalidData`.
+pub struct BlobReader {
+    info: BlobInfo,
+    state: ReadState,
+}
+
+impl BlobReader {
+    pub(crate) async fn open(
+        mut snapshot: Snapshot,
+        config: Arc<DbConfig>,
+        collection: &str,
+        id: &str,
+        name: &str,
+    ) -> Result<Option<Self>, OsunbitDBError> {
+        let target = BlobTarget::new(&config.limits, collection, id, name)?;
+        let Some(stored) = read_meta(&mut Reader::Snapshot(&mut snapshot), &target).await? else {
+            return Ok(None);
+        };
+        let info = stored.info(name);
+        let download = Download {
+            snapshot,
+            config,
+            target,
+            stored,
+            next: 0,
+            current: Vec::new(),
+            pos: 0,
+            hasher: Sha256::new(),
+        };
+        Ok(Some(BlobReader { info, state: ReadState::Idle(Box::new(download)) }))
+    }
+
+    pub fn info(&self) -> &BlobInfo {
+        &self.info
+    }
+}
+
+impl AsyncRead for BlobReader {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let ReadState::Busy(fetch) = &mut this.state {
+                let (mut download, res) = ready!(fetch.as_mut().poll(cx));
+                let fetched = res.map(|chunk| {
+                    download.hasher.update(&chunk);
+                    download.current = chunk;
+                    download.pos = 0;
+                    download.next += 1;
+                });
+                this.state = ReadState::Idle(download);
+                fetched.map_err(|e| io::Error::other(e.to_string()))?;
+            }
+            let ReadState::Idle(download) = &mut this.state else {
+                unreachable!("blob reader lost its download");
+            };
+            if download.pos < download.current.len() {
+                let n = buf.remaining().min(download.current.len() - download.pos);
+                buf.put_slice(&download.current[download.pos..download.pos + n]);
+                download.pos += n;
+                return Poll::Ready(Ok(()));
+            }
+            if download.next >= download.stored.chunks {
+                if hex(&download.hasher.clone().finalize()) != download.stored.sha256 {
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, "blob does not match its SHA-256")));
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            let ReadState::Idle(mut download) = std::mem::replace(&mut this.state, ReadState::Empty) else {
+                unreachable!();
+            };
+            this.state = ReadState::Busy(Box::pin(async move {
+                let res = read_chunk(
+                    &mut Reader::Snapshot(&mut download.snapshot),
+                    &download.config,
+                    &download.target,
+                    &download.stored,
+                    download.next,
+                )
+                .await;
+                (download, res)
+            }));
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Chunked bulk writes for inputs too large for a single TiKV transaction.
+//!
+//! **Not atomic across chunks.** Each chunk commits in its own transaction, so a
+//! failed run can leave some chunks written and others not. Check
+//! `BulkSummary::failed` and re-run (writes are idempotent) or clean up.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serde_json::Value as JsonValue;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use crate::client::OsunbitDB;
+use crate::errors::OsunbitDBError;
+
+/// Called after every chunk finishes (successfully or after its last retry).
+pub type ProgressFn = Arc<dyn Fn(&ChunkProgress) + Send + Sync>;
+
+/// Limits and retry policy for `bulk_add` / `bulk_delete`.
+#[derive(Clone)]
+pub struct BulkOptions {
+    /// Max keys per chunk.
+    pub max_keys: usize,
+    /// Max encoded bytes (keys + values) per chunk.
+    pub max_bytes: usize,
+    /// Chunks in flight at the same time.
+    pub concurrency: usize,
+    /// Extra attempts for a chunk that failed with a retryable error, see
+    /// `OsunbitDBError::is_retryable`.
+    pub max_retries: u32,
+    /// Wait before retry `n` is `retry_backoff * n`.
+    pub retry_backoff: Duration,
+    pub on_progress: Option<ProgressFn>,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        Self {
+            max_keys: 1000,
+            max_bytes: 4 * 1024 * 1024,
+            concurrency: 4,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(100),
+            on_progress: None,
+        }
+    }
+}
+
+impl BulkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    pub fn on_progress<F: Fn(&ChunkProgress) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+}
+
+/// Progress report for one chunk.
+#[derive(Debug, Clone)]
+pub struct ChunkProgress {
+    /// Zero-based chunk index.
+    pub chunk: usize,
+    pub total_chunks: usize,
+    pub keys: usize,
+    pub bytes: usize,
+    pub attempts: u32,
+    /// `None` when the chunk committed.
+    pub error: Option<String>,
+}
+
+/// A chunk that still failed after all retries.
+#[derive(Debug, Clone)]
+pub struct ChunkFailure {
+    pub chunk: usize,
+    pub ids: Vec<String>,
+    pub error: String,
+}
+
+/// Final report of a bulk run.
+#[derive(Debug, Clone, Default)]
+pub struct BulkSummary {
+    pub total_chunks: usize,
+    pub succeeded_chunks: usize,
+    pub written_keys: usize,
+    pub written_bytes: usize,
+    /// Retries across all chunks.
+    pub retries: u32,
+    pub failed: Vec<ChunkFailure>,
+    pub elapsed: Duration,
+}
+
+impl BulkSummary {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[derive(Clone)]
+enum BulkOp {
+    Add(Vec<(String, JsonValue)>),
+    Delete(Vec<String>),
+}
+
+impl BulkOp {
+    fn ids(&self) -> Vec<String> {
+        match self {
+            BulkOp::Add(items) => items.iter().map(|(id, _)| id.clone()).collect(),
+            BulkOp::Delete(ids) => ids.clone(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            BulkOp::Add(items) => items.len(),
+            BulkOp::Delete(ids) => ids.len(),
+        }
+    }
+}
+
+struct Chunk {
+    op: BulkOp,
+    bytes: usize,
+}
+
+/// Greedily pack `(id, value, bytes)` entries into chunks under both limits.
+/// A single entry bigger than `max_bytes` still gets its own chunk.
+fn split<T>(entries: Vec<(T, usize)>, max_keys: usize, max_bytes: usize) -> Vec<(Vec<T>, usize)> {
+    let max_keys = max_keys.max(1);
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = 0;
+
+    for (entry, bytes) in entries {
+        if !current.is_empty() && (current.len() >= max_keys || current_bytes + bytes > max_bytes) {
+            chunks.push((std::mem::take(&mut current), current_bytes));
+            current_bytes = 0;
+        }
+        current.push(entry);
+        current_bytes += bytes;
+    }
+    if !current.is_empty() {
+        chunks.push((current, current_bytes));
+    }
+    chunks
+}
+
+async fn write_chunk(db: &OsunbitDB, collection: &str, op: &BulkOp) -> Result<(), OsunbitDBError> {
+    let mut tx = db.transaction().await?;
+    let res = match op {
+        BulkOp::Add(items) => {
+            let mut res = Ok(());
+            for (id, value) in items {
+                res = tx.add(collection, id, value).await;
+                if res.is_err() {
+                    break;
+                }
+            }
+            res
+        }
+        BulkOp::Delete(ids) => {
+            let mut res = Ok(());
+            for id in ids {
+                res = tx.delete(collection, id).await;
+                if res.is_err() {
+                    break;
+                }
+            }
+            res
+        }
+    };
+
+    match res {
+        Ok(()) => tx.commit().await,
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+async fn run(db: &OsunbitDB, collection: &str, chunks: Vec<Chunk>, opts: &BulkOptions) -> Result<BulkSummary, OsunbitDBError> {
+    let started = Instant::now();
+    let total_chunks = chunks.len();
+    let semaphore = Arc::new(Semaphore::new(opts.concurrency.max(1)));
+    let mut set = JoinSet::new();
+    // Chunk index and ids per task, to report a task that panicked
+    let mut pending = HashMap::new();
+
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let ids = chunk.op.ids();
+        let db = db.clone();
+        let collection = collection.to_string();
+        let semaphore = semaphore.clone();
+        let opts = opts.clone();
+
+        let task = set.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.expect("bulk semaphore closed");
+            let mut attempts = 0;
+            let result = loop {
+                attempts += 1;
+                match write_chunk(&db, &collection, &chunk.op).await {
+                    Ok(()) => break Ok(()),
+                    Err(e) if attempts > opts.max_retries || !e.is_retryable() => break Err(e.to_string()),
+                    Err(_) => tokio::time::sleep(opts.retry_backoff * attempts).await,
+                }
+            };
+
+            let progress = ChunkProgress {
+                chunk: index,
+                total_chunks,
+                keys: chunk.op.len(),
+                bytes: chunk.bytes,
+                attempts,
+                error: result.as_ref().err().cloned(),
+            };
+            if let Some(cb) = &opts.on_progress {
+                cb(&progress);
+            }
+            progress
+        });
+        pending.insert(task.id(), (index, ids));
+    }
+
+    let mut summary = BulkSummary { total_chunks, ..Default::default() };
+    while let Some(joined) = set.join_next_with_id().await {
+        let (task, outcome) = match joined {
+            Ok((task, progress)) => (task, Ok(progress)),
+            Err(e) => (e.id(), Err(format!("chunk task failed: {}", e))),
+        };
+        let (chunk, ids) = pending.remove(&task).expect("every bulk task is tracked");
+        let progress = match outcome {
+            Ok(progress) => progress,
+            Err(error) => {
+                summary.failed.push(ChunkFailure { chunk, ids, error });
+                continue;
+            }
+        };
+        summary.retries += progress.attempts - 1;
+        match progress.error {
+            None => {
+                summary.succeeded_chunks += 1;
+                summary.written_keys += progress.keys;
+                summary.written_bytes += progress.bytes;
+            }
+            Some(error) => summary.failed.push(ChunkFailure { chunk, ids, error }),
+        }
+    }
+    summary.failed.sort_by_key(|f| f.chunk);
+    summary.elapsed = started.elapsed();
+    Ok(summary)
+}
+
+pub(crate) async fn bulk_add(db: &OsunbitDB, collection: &str, items_json: &JsonValue, opts: &BulkOptions) -> Result<BulkSummary, OsunbitDBError> {
+    let map = items_json.as_object().ok_or_else(|| {
+        OsunbitDBError::invalid_update("bulk_add expects a JSON object".to_string())
+    })?;
+
+    let limits = &db.config.limits;
+    limits.check_collection(collection)?;
+    let mut entries = Vec::with_capacity(map.len());
+    for (id, value) in map {
+        limits.check_id(id)?;
+        limits.check_document(value)?;
+        let bytes = collection.len() + 1 + id.len() + serde_json::to_vec(value)?.len();
+        entries.push(((id.clone(), value.clone()), bytes));
+    }
+
+    let chunks = split(entries, opts.max_keys, opts.max_bytes)
+        .into_iter()
+        .map(|(items, bytes)| Chunk { op: BulkOp::Add(items), bytes })
+        .collect();
+    run(db, collection, chunks, opts).await
+}
+
+pub(crate) async fn bulk_delete(db: &OsunbitDB, collection: &str, ids_json: &JsonValue, opts: &BulkOptions) -> Result<BulkSummary, OsunbitDBError> {
+    let arr = ids_json.as_array().ok_or_else(|| {
+        OsunbitDBError::invalid_update("bulk_delete expects a JSON array of ids".to_string())
+    })?;
+
+    let limits = &db.config.limits;
+    limits.check_collection(collection)?;
+    let mut entries = Vec::with_capacity(arr.len());
+    for id in arr.iter().filter_map(|id| id.as_str()) {
+        limits.check_id(id)?;
+        entries.push((id.to_string(), collection.len() + 1 + id.len()));
+    }
+
+    let chunks = split(entries, opts.max_keys, opts.max_bytes)
+        .into_iter()
+        .map(|(ids, bytes)| Chunk { op: BulkOp::Delete(ids), bytes })
+        .collect();
+    run(db, collection, chunks, opts).await
+}
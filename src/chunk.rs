@@ -0,0 +1,88 @@
+use sha2::{Digest, Sha256};
+use tikv_rust::Key;
+use crate::errors::OsunbitDBError;
+use crate::utils::meta_key;
+
+/// Largest value stored under a single key by default. TiKV rejects raft
+/// entries above 8 MiB, so this leaves room for the rest of the transaction.
+pub const DEFAULT_CHUNK_SIZE: usize = 2 * 1024 * 1024;
+
+/// Value at the document key of a chunked value. Not a valid start of any
+/// other stored format, including legacy JSON.
+const MANIFEST_TAG: u8 = 0x0C;
+const MANIFEST_LEN: usize = 1 + 4 + 8 + 32;
+
+/// Where the chunks of a large value are and how to check them: stored as
+/// tag, chunk count (u32 BE), total length (u64 BE) and SHA-256 of the value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Manifest {
+    pub(crate) count: u32,
+    len: u64,
+    digest: [u8; 32],
+}
+
+impl Manifest {
+    pub(crate) fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != MANIFEST_LEN || bytes[0] != MANIFEST_TAG {
+            return None;
+        }
+        Some(Manifest {
+            count: u32::from_be_bytes(bytes[1..5].try_into().ok()?),
+            len: u64::from_be_bytes(bytes[5..13].try_into().ok()?),
+            digest: bytes[13..].try_into().ok()?,
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MANIFEST_LEN);
+        out.push(MANIFEST_TAG);
+        out.extend_from_slice(&self.count.to_be_bytes());
+        out.extend_from_slice(&self.len.to_be_bytes());
+        out.extend_from_slice(&self.digest);
+        out
+    }
+
+    /// Keys of every chunk, in order.
+    pub(crate) fn keys(&self, doc_key: &Key) -> Vec<Key> {
+        (0..self.count).map(|i| chunk_key(doc_key, i)).collect()
+    }
+
+    /// Join `chunks` (in `keys` order) back into the value, checking length and digest.
+    pub(crate) fn assemble(&self, chunks: Vec<Option<Vec<u8>>>) -> Result<Vec<u8>, OsunbitDBError> {
+        let mut out = Vec::with_capacity(self.len as usize);
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let chunk = chunk.ok_or_else(|| OsunbitDBError::corruption(format!("chunk {} of {} is missing", i, self.count)))?;
+            out.extend(chunk);
+        }
+        if out.len() as u64 != self.len || Sha256::digest(&out).as_slice() != self.digest {
+            return Err(OsunbitDBError::corruption("chunked value does not match its manifest".to_string()));
+        }
+        Ok(out)
+    }
+}
+
+/// True for the manifest of a chunked value, whose chunks could not be read.
+pub(crate) fn is_manifest(bytes: &[u8]) -> bool {
+    Manifest::parse(bytes).is_some()
+}
+
+/// Internal key of chunk `index` of the value at `doc_key`. Chunks live under
+/// the meta prefix, so collection scans never see them.
+pub(crate) fn chunk_key(doc_key: &Key, index: u32) -> Key {
+    let mut key: Vec<u8> = meta_key("chunk", "").into();
+    key.extend_from_slice(doc_key.into());
+    key.extend_from_slice(format!(":{:08}", index).as_bytes());
+    Key::from(key)
+}
+
+/// Split `bytes` into chunks of at most `size` bytes. Returns the manifest to
+/// store at the document key and the chunks in key order.
+pub(crate) fn split(bytes: &[u8], size: usize) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let chunks: Vec<Vec<u8>> = bytes.chunks(size.max(1)).map(<[u8]>::to_vec).collect();
+    let manifest = Manifest {
+        count: chunks.len() as u32,
+        len: bytes.len() as u64,
+        digest: Sha256::digest(bytes).into(),
+    };
+    (manifest.to_bytes(), chunks)
+}
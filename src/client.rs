@@ -1,78 +1,513 @@
-use tikv_rust::TransactionClient;
+use futures::stream::{BoxStream, StreamExt};
+use std::sync::Arc;
+use tikv_rust::{CheckLevel, Snapshot, Timestamp, TimestampExt, TransactionClient, TransactionOptions};
 use serde_json::Value as JsonValue;
-use crate::errors::OsunbitDBError;
-use crate::transaction::TransactionHandle;
+use crate::aggregate::{self, AggregateResult, Aggregation};
+use crate::blob::{self, Blob, BlobInfo, BlobReader, BlobWriter};
+use crate::codec;
+use crate::bulk::{self, BulkOptions, BulkSummary};
+use crate::errors::{OsunbitDBError, ResultExt};
+use crate::filter::Filter;
+use crate::group::{self, CollectionGroup, GroupResult};
+use crate::projection::FieldMask;
+use crate::config::{CollectionConfig, DbConfig};
+use crate::encryption::KeyProvider;
+use crate::ids::IdGenerator;
+use crate::validation::Limits;
+use crate::cursor::Cursor;
+use crate::scan::{self, CollectionStats, Consistency, Reader, ScanOptions, ScanResult, StreamOptions};
+use crate::ops::IntoUpdate;
+use crate::transaction::{BatchGetResult, RewriteJob, TransactionHandle, WriteResult};
 
 #[derive(Clone)]
 pub struct OsunbitDB {
     client: TransactionClient, 
+    pub(crate) config: Arc<DbConfig>,
 }
 
 impl OsunbitDB {
     pub async fn new<S: Into<String> + Clone>(pds: &[S]) -> Result<Self, OsunbitDBError> {
-        let client = TransactionClient::new(pds.to_vec()).await?;
-        Ok(Self { client })
+        async {
+            let client = TransactionClient::new(pds.to_vec()).await?;
+            Ok(Self { client, config: Arc::new(DbConfig::default()) })
+        }
+        .await
+        .with_context("connect", "", None)
+    }
+
+    /// Sign pagination cursors with HMAC-SHA256 under `key`. Unsigned or
+    /// tampered cursors are then rejected.
+    pub fn with_cursor_key(mut self, key: &[u8]) -> Self {
+        Arc::make_mut(&mut self.config).cursor_key = Some(key.to_vec());
+        self
+    }
+
+    /// Master keys for collections configured with `CollectionConfig::encryption`.
+    pub fn with_key_provider(mut self, provider: impl KeyProvider + 'static) -> Self {
+        Arc::make_mut(&mut self.config).key_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Split values larger than `bytes` across several keys (default
+    /// `DEFAULT_CHUNK_SIZE`). They are reassembled on read, so documents can
+    /// exceed TiKV's per-entry limit; keep it well below 8 MiB.
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        Arc::make_mut(&mut self.config).chunk_size = Some(bytes.max(1));
+        self
+    }
+
+    /// Limits on names and documents checked on every call, see `Limits`.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        Arc::make_mut(&mut self.config).limits = limits;
+        self
+    }
+
+    /// Ciphertext stored for `value` in the deterministic encrypted field `path`
+    /// of `collection` (under the current key), e.g. to look it up in an
+    /// external index or raw export.
+    pub fn field_token(&self, collection: &str, path: &str, value: &JsonValue) -> Result<JsonValue, OsunbitDBError> {
+        codec::field_token(&self.config, collection, path, value).with_context("field_token", collection, None)
+    }
+
+    /// Register settings for a collection, or for a pattern like `users:*:inbox`
+    /// where `*` matches one path segment. An exact name wins over a pattern.
+    pub fn with_collection(mut self, collection: &str, config: CollectionConfig) -> Self {
+        Arc::make_mut(&mut self.config).collections.insert(collection.to_string(), config);
+        self
+    }
+
+    /// Read-only snapshot at the current timestamp.
+    pub(crate) async fn snapshot(&self) -> Result<Snapshot, OsunbitDBError> {
+        let ts = self.client.current_timestamp().await?;
+        Ok(scan::read_snapshot(&self.client, ts))
     }
 
     pub async fn transaction(&self) -> Result<TransactionHandle, OsunbitDBError> {
-        let tx = self.client.begin_pessimistic().await?;
-        Ok(TransactionHandle { tx })
+        async {
+            let tx = self.client.begin_pessimistic().await?;
+            Ok(TransactionHandle::new(tx, self.config.clone()))
+        }
+        .await
+        .with_context("transaction", "", None)
     }
 
     pub async fn add(&self, collection: &str, id: &str, value: &JsonValue) -> Result<(), OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        tx.add(collection, id, value).await?;
-        tx.commit().await?;
-        Ok(())
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.add(collection, id, value).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("add", collection, Some(id))
+    }
+
+    /// Insert only: fails with `AlreadyExists` if `collection/id` is stored.
+    pub async fn create(&self, collection: &str, id: &str, value: &JsonValue) -> Result<(), OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.create(collection, id, value).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("create", collection, Some(id))
+    }
+
+    /// Add under a generated id (see `CollectionConfig::id_generator`) and return it.
+    pub async fn add_auto(&self, collection: &str, value: &JsonValue) -> Result<String, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.add_auto(collection, value).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("add_auto", collection, None)
+    }
+
+    pub async fn add_auto_with(&self, collection: &str, value: &JsonValue, generator: &IdGenerator) -> Result<String, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.add_auto_with(collection, value, generator).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("add_auto_with", collection, None)
     }
 
     pub async fn get(&self, collection: &str, id: &str) -> Result<Option<JsonValue>, OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        let result = tx.get(collection, id).await?;
-        tx.rollback().await?;
-        Ok(result)
+        async {
+            let mut tx = self.transaction().await?;
+            let result = tx.get(collection, id).await;
+            tx.rollback().await?;
+            result
+        }
+        .await
+        .with_context("get", collection, Some(id))
+    }
+
+    /// Check whether a document exists without reading its value.
+    pub async fn exists(&self, collection: &str, id: &str) -> Result<bool, OsunbitDBError> {
+        async {
+            self.config.limits.check_key(collection, id)?;
+            let mut snapshot = self.snapshot().await?;
+            scan::exists(&mut Reader::Snapshot(&mut snapshot), collection, id).await
+        }
+        .await
+        .with_context("exists", collection, Some(id))
+    }
+
+    /// Count documents using keys-only scans. Bounds, prefix and filter come from
+    /// `opts` (a filter makes it read values); limit and cursor are ignored.
+    pub async fn count(&self, collection: &str, opts: &ScanOptions) -> Result<u64, OsunbitDBError> {
+        async {
+            let mut snapshot = self.snapshot().await?;
+            scan::count(&mut Reader::Snapshot(&mut snapshot), &self.config, collection, opts).await
+        }
+        .await
+        .with_context("count", collection, None)
+    }
+
+    /// Document count, total value bytes, min/max id and largest document.
+    /// Reads every value of the collection.
+    pub async fn stats(&self, collection: &str) -> Result<CollectionStats, OsunbitDBError> {
+        async {
+            self.config.limits.check_collection(collection)?;
+            let mut snapshot = self.snapshot().await?;
+            scan::stats(&mut Reader::Snapshot(&mut snapshot), collection).await
+        }
+        .await
+        .with_context("stats", collection, None)
+    }
+
+    /// Aggregate over the whole collection in one streaming pass; memory grows
+    /// with the number of groups, not documents.
+    pub async fn aggregate(&self, collection: &str, agg: &Aggregation) -> Result<AggregateResult, OsunbitDBError> {
+        async {
+            let mut snapshot = self.snapshot().await?;
+            aggregate::aggregate(&mut Reader::Snapshot(&mut snapshot), &self.config, collection, agg).await
+        }
+        .await
+        .with_context("aggregate", collection, None)
+    }
+
+    /// Query every collection whose last path segment is `query.name`, e.g. all
+    /// `users:{uid}:inbox`. Scans the keys of the whole keyspace unless `within`
+    /// narrows it; undecodable documents are reported in `GroupResult::corrupt`.
+    pub async fn collection_group(&self, query: &CollectionGroup) -> Result<GroupResult, OsunbitDBError> {
+        async {
+            let mut snapshot = self.snapshot().await?;
+            group::collection_group(&mut Reader::Snapshot(&mut snapshot), &self.config, query).await
+        }
+        .await
+        .with_context("collection_group", &query.name, None)
+    }
+
+    /// Direct child collection names under `parent` (`""` for the root, else a
+    /// document path), e.g. `list_collections("users:u1")` gives `["inbox", ...]`.
+    /// A collection path like `"users"` is rejected with `InvalidArgument`. Uses
+    /// one keys-only read per child instead of scanning values.
+    pub async fn list_collections(&self, parent: &str) -> Result<Vec<String>, OsunbitDBError> {
+        async {
+            self.config.limits.check_parent(parent)?;
+            let mut snapshot = self.snapshot().await?;
+            group::list_collections(&mut Reader::Snapshot(&mut snapshot), parent).await
+        }
+        .await
+        .with_context("list_collections", parent, None)
+    }
+
+    pub async fn get_masked(&self, collection: &str, id: &str, mask: &FieldMask) -> Result<Option<JsonValue>, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let result = tx.get_masked(collection, id, mask).await;
+            tx.rollback().await?;
+            result
+        }
+        .await
+        .with_context("get_masked", collection, Some(id))
     }
 
     pub async fn delete(&self, collection: &str, id: &str) -> Result<(), OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        tx.delete(collection, id).await?;
-        tx.commit().await?;
-        Ok(())
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.delete(collection, id).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("delete", collection, Some(id))
+    }
+
+    /// Delete a document, its subcollections at any depth and all their blobs
+    /// in one transaction. Returns the number of documents removed.
+    pub async fn delete_recursive(&self, collection: &str, id: &str) -> Result<u64, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.delete_recursive(collection, id).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("delete_recursive", collection, Some(id))
+    }
+
+    /// Store `data` as blob `name` of the existing document `collection/id`,
+    /// chunked like large documents and encrypted if the collection is.
+    pub async fn put_blob(&self, collection: &str, id: &str, name: &str, content_type: &str, data: &[u8]) -> Result<BlobInfo, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.put_blob(collection, id, name, content_type, data).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("put_blob", collection, Some(id))
     }
 
-    pub async fn update(&self, collection: &str, id: &str, fields: &JsonValue) -> Result<(), OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        tx.update(collection, id, fields).await?;
-        tx.commit().await?;
-        Ok(())
+    /// Read blob `name` of `collection/id` into memory, see `blob_reader` for large ones.
+    pub async fn get_blob(&self, collection: &str, id: &str, name: &str) -> Result<Option<Blob>, OsunbitDBError> {
+        async {
+            let mut snapshot = self.snapshot().await?;
+            blob::get_blob(&mut Reader::Snapshot(&mut snapshot), &self.config, collection, id, name).await
+        }
+        .await
+        .with_context("get_blob", collection, Some(id))
+    }
+
+    /// Metadata of every blob of `collection/id`.
+    pub async fn list_blobs(&self, collection: &str, id: &str) -> Result<Vec<BlobInfo>, OsunbitDBError> {
+        async {
+            let mut snapshot = self.snapshot().await?;
+            blob::list_blobs(&mut Reader::Snapshot(&mut snapshot), &self.config, collection, id).await
+        }
+        .await
+        .with_context("list_blobs", collection, Some(id))
+    }
+
+    /// Remove blob `name` of `collection/id`, returns whether it existed.
+    pub async fn delete_blob(&self, collection: &str, id: &str, name: &str) -> Result<bool, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.delete_blob(collection, id, name).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("delete_blob", collection, Some(id))
+    }
+
+    /// Upload blob `name` of `collection/id` through `AsyncWrite` in its own
+    /// transaction; call `BlobWriter::commit` when done. Dropping the writer
+    /// instead abandons the upload.
+    pub async fn blob_writer(&self, collection: &str, id: &str, name: &str, content_type: &str) -> Result<BlobWriter, OsunbitDBError> {
+        let options = TransactionOptions::new_pessimistic().drop_check(CheckLevel::Warn);
+        let tx = self.client.begin_with_options(options).await.map_err(|e| {
+            OsunbitDBError::from(e).with_context("blob_writer", collection, Some(id))
+        })?;
+        TransactionHandle::new(tx, self.config.clone()).blob_writer(collection, id, name, content_type)
+    }
+
+    /// `AsyncRead` over blob `name` of `collection/id` at the current
+    /// snapshot, `None` if there is no such blob.
+    pub async fn blob_reader(&self, collection: &str, id: &str, name: &str) -> Result<Option<BlobReader>, OsunbitDBError> {
+        async {
+            let snapshot = self.snapshot().await?;
+            BlobReader::open(snapshot, self.config.clone(), collection, id, name).await
+        }
+        .await
+        .with_context("blob_reader", collection, Some(id))
+    }
+
+    pub async fn update(&self, collection: &str, id: &str, fields: impl IntoUpdate) -> Result<(), OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.update(collection, id, fields).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("update", collection, Some(id))
+    }
+
+    pub async fn add_returning(&self, collection: &str, id: &str, value: &JsonValue) -> Result<WriteResult, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.add_returning(collection, id, value).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("add_returning", collection, Some(id))
+    }
+
+    pub async fn update_returning(&self, collection: &str, id: &str, fields: impl IntoUpdate) -> Result<WriteResult, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.update_returning(collection, id, fields).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("update_returning", collection, Some(id))
+    }
+
+    pub async fn delete_returning(&self, collection: &str, id: &str) -> Result<WriteResult, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.delete_returning(collection, id).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("delete_returning", collection, Some(id))
     }
 
     pub async fn scan(&self, collection: &str, limit: u32, cursor: &str, order: &str,) -> Result<JsonValue, OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        let result = tx.scan(collection, limit, cursor, order).await?;
-        tx.rollback().await?;
-        Ok(result)
+        async {
+            let mut tx = self.transaction().await?;
+            let result = tx.scan(collection, limit, cursor, order).await;
+            tx.rollback().await?;
+            result
+        }
+        .await
+        .with_context("scan", collection, None)
+    }
+
+    /// Reads from a snapshot. With `Consistency::Snapshot` and a cursor, the page is
+    /// read at the cursor's timestamp so all pages see the same data.
+    pub async fn scan_with(&self, collection: &str, opts: &ScanOptions) -> Result<ScanResult, OsunbitDBError> {
+        async {
+            let cursor_key = self.config.cursor_key.as_deref();
+            let cursor_ts = match (&opts.cursor, opts.consistency) {
+                (Some(token), Consistency::Snapshot) => Some(Cursor::decode(token, cursor_key)?.snapshot_ts),
+                _ => None,
+            };
+            let ts = match cursor_ts {
+                Some(version) => Timestamp::from_version(version),
+                None => self.client.current_timestamp().await?,
+            };
+
+            let version = ts.version();
+            let mut snapshot = scan::read_snapshot(&self.client, ts);
+            scan::scan_with(Reader::Snapshot(&mut snapshot), &self.config, collection, opts, version).await
+        }
+        .await
+        .with_context("scan_with", collection, None)
     }
 
+    /// Iterate a whole collection as `(id, document)` pairs, paging internally.
+    /// Subcollection documents are skipped and undecodable ones come back as
+    /// `Null`, as in `scan`.
+    pub fn stream(&self, collection: &str, opts: StreamOptions) -> BoxStream<'static, Result<(String, JsonValue), OsunbitDBError>> {
+        let context = collection.to_string();
+        scan::stream(self.client.clone(), self.config.clone(), collection, opts)
+            .map(move |item| item.with_context("stream", &context, None))
+            .boxed()
+    }
+
+    /// Atomic: all items go into one transaction. For inputs that may exceed the
+    /// TiKV transaction size limit use `bulk_add`.
     pub async fn batch_add(&self, collection: &str, items_json: &JsonValue) -> Result<(), OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        tx.batch_add(collection, items_json).await?;
-        tx.commit().await?;
-        Ok(())
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.batch_add(collection, items_json).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("batch_add", collection, None)
+    }
+
+    /// Chunked, concurrent `batch_add` for large inputs. Not atomic across chunks,
+    /// see `BulkSummary::failed` for chunks that did not commit.
+    pub async fn bulk_add(&self, collection: &str, items_json: &JsonValue, opts: &BulkOptions) -> Result<BulkSummary, OsunbitDBError> {
+        bulk::bulk_add(self, collection, items_json, opts).await
+            .with_context("bulk_add", collection, None)
+    }
+
+    /// Chunked, concurrent `batch_delete` for large inputs. Not atomic across chunks.
+    pub async fn bulk_delete(&self, collection: &str, ids_json: &JsonValue, opts: &BulkOptions) -> Result<BulkSummary, OsunbitDBError> {
+        bulk::bulk_delete(self, collection, ids_json, opts).await
+            .with_context("bulk_delete", collection, None)
+    }
+
+    /// Re-encode every document of `collection` with its current settings, e.g.
+    /// after changing its codec or compression, or to re-encrypt under the
+    /// provider's current key after a rotation. Blob chunks of each document
+    /// are re-sealed the same way. Documents already current are skipped.
+    /// One transaction per `page_size` documents, so not atomic as a whole;
+    /// progress is checkpointed and an interrupted run resumes where it
+    /// stopped. Returns the number of documents rewritten by this call,
+    /// counting those where only blobs changed.
+    pub async fn rewrite(&self, collection: &str, page_size: u32) -> Result<u64, OsunbitDBError> {
+        self.run_rewrite(collection, RewriteJob::Rewrite, page_size).await
+            .with_context("rewrite", collection, None)
+    }
+
+    /// Eagerly migrate every document of `collection` below the current schema
+    /// version (see `CollectionConfig::migration`), in checkpointed page-sized
+    /// transactions like `rewrite`. Safe to run next to normal traffic and from
+    /// several processes, e.g. `tokio::spawn` it at startup.
+    pub async fn migrate(&self, collection: &str, page_size: u32) -> Result<u64, OsunbitDBError> {
+        self.run_rewrite(collection, RewriteJob::Migrate, page_size).await
+            .with_context("migrate", collection, None)
+    }
+
+    async fn run_rewrite(&self, collection: &str, job: RewriteJob, page_size: u32) -> Result<u64, OsunbitDBError> {
+        self.config.limits.check_collection(collection)?;
+        let page_size = page_size.clamp(1, scan::MAX_SCAN_LIMIT);
+        let mut total = 0;
+        loop {
+            let mut tx = self.transaction().await?;
+            let res = tx.rewrite_page(collection, job, page_size).await;
+            let (n, done) = tx.finish(res).await?;
+            total += n;
+            if done {
+                return Ok(total);
+            }
+        }
+    }
+
+    pub async fn batch_get(&self, collection: &str, ids_json: &JsonValue) -> Result<BatchGetResult, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.batch_get(collection, ids_json).await;
+            let _ = tx.rollback().await;
+            res
+        }
+        .await
+        .with_context("batch_get", collection, None)
+    }
+
+    pub async fn batch_update(&self, collection: &str, updates_json: &JsonValue) -> Result<(), OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.batch_update(collection, updates_json).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("batch_update", collection, None)
+    }
+
+    pub async fn update_where(&self, collection: &str, filter: &Filter, fields: impl IntoUpdate) -> Result<Vec<String>, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.update_where(collection, filter, fields).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("update_where", collection, None)
     }
 
-    pub async fn batch_get(&self, collection: &str, ids_json: &JsonValue) -> Result<JsonValue, OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        let res = tx.batch_get(collection, ids_json).await?;
-        let _ = tx.rollback().await;
-        Ok(res)
+    pub async fn batch_get_masked(&self, collection: &str, ids_json: &JsonValue, mask: &FieldMask) -> Result<BatchGetResult, OsunbitDBError> {
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.batch_get_masked(collection, ids_json, mask).await;
+            let _ = tx.rollback().await;
+            res
+        }
+        .await
+        .with_context("batch_get_masked", collection, None)
     }
 
     pub async fn batch_delete(&self, collection: &str, ids_json: &JsonValue) -> Result<(), OsunbitDBError> {
-        let mut tx = self.transaction().await?;
-        tx.batch_delete(collection, ids_json).await?;
-        tx.commit().await?;
-        Ok(())
+        async {
+            let mut tx = self.transaction().await?;
+            let res = tx.batch_delete(collection, ids_json).await;
+            tx.finish(res).await
+        }
+        .await
+        .with_context("batch_delete", collection, None)
     }
    
 
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value as JsonValue};
+use crate::chunk;
+use crate::compression::Compression;
+use crate::config::{CollectionConfig, DbConfig};
+use crate::encryption::{self, FieldEncryption, KeyProvider};
+use crate::errors::OsunbitDBError;
+use crate::utils::{decode, encode, get_deep, set_deep};
+
+/// Serialization format of stored documents, chosen per collection with
+/// `CollectionConfig::codec`. `MessagePack` and `Cbor` need the `codecs`
+/// feature, without it they fail with `OsunbitDBError::Codec`.
+///
+/// Every stored value starts with the codec's tag byte, so a collection can
+/// switch codecs and still read what was written before. Values without a
+/// known tag are legacy untagged JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+    /// Compact binary via `bincode`; JSON values go through a tagged mirror
+    /// type since bincode is not self-describing.
+    Bincode,
+}
+
+impl Codec {
+    pub fn tag(self) -> u8 {
+        match self {
+            Codec::Json => 0x01,
+            Codec::MessagePack => 0x02,
+            Codec::Cbor => 0x03,
+            Codec::Bincode => 0x04,
+        }
+    }
+
+    /// `None` for bytes that are not a codec tag (legacy JSON starts with
+    /// `{`, `[`, a digit, a quote or whitespace, never a control byte).
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0x01 => Some(Codec::Json),
+            0x02 => Some(Codec::MessagePack),
+            0x03 => Some(Codec::Cbor),
+            0x04 => Some(Codec::Bincode),
+            _ => None,
+        }
+    }
+
+    /// Tag byte followed by the serialized document.
+    pub fn encode(self, doc: &JsonValue) -> Result<Vec<u8>, OsunbitDBError> {
+        let mut out = vec![self.tag()];
+        match self {
+            Codec::Json => serde_json::to_writer(&mut out, doc)?,
+            #[cfg(feature = "codecs")]
+            Codec::MessagePack => out.extend(
+                rmp_serde::to_vec_named(doc).map_err(|e| OsunbitDBError::codec(e.to_string()))?,
+            ),
+            #[cfg(feature = "codecs")]
+            Codec::Cbor => ciborium::into_writer(doc, &mut out).map_err(|e| OsunbitDBError::codec(e.to_string()))?,
+            #[cfg(not(feature = "codecs"))]
+            Codec::MessagePack | Codec::Cbor => return Err(self.disabled()),
+            Codec::Bincode => out.extend(encode(&BinValue::from(doc))?),
+        }
+        Ok(out)
+    }
+
+    fn decode_body(self, body: &[u8]) -> Result<JsonValue, OsunbitDBError> {
+        match self {
+            Codec::Json => Ok(serde_json::from_slice(body)?),
+            #[cfg(feature = "codecs")]
+            Codec::MessagePack => rmp_serde::from_slice(body).map_err(|e| OsunbitDBError::corruption(e.to_string())),
+            #[cfg(feature = "codecs")]
+            Codec::Cbor => ciborium::from_reader(body).map_err(|e| OsunbitDBError::corruption(e.to_string())),
+            #[cfg(not(feature = "codecs"))]
+            Codec::MessagePack | Codec::Cbor => Err(self.disabled()),
+            Codec::Bincode => Ok(decode::<BinValue>(body)?.into()),
+        }
+    }
+
+    #[cfg(not(feature = "codecs"))]
+    fn disabled(self) -> OsunbitDBError {
+        OsunbitDBError::codec(format!("{:?} needs the `codecs` feature", self))
+    }
+
+    /// Decode a stored value by its tag, whichever codec wrote it.
+    pub fn decode(bytes: &[u8]) -> Result<JsonValue, OsunbitDBError> {
+        match bytes.first().copied().and_then(Codec::from_tag) {
+            Some(codec) => codec.decode_body(&bytes[1..]),
+            None => Ok(serde_json::from_slice(bytes)?),
+        }
+    }
+}
+
+/// Header of a value written under a schema version above 1: tag, then the
+/// version as big-endian u32.
+const VERSION_TAG: u8 = 0x08;
+
+/// Storage key of a document, what its encryption is bound to.
+fn doc_key(collection: &str, id: &str) -> Vec<u8> {
+    format!("{}:{}", collection, id).into_bytes()
+}
+
+/// Serialize document `id` of `collection`: codec, schema version, compression,
+/// then encryption, each as configured.
+pub(crate) fn encode_doc(config: &DbConfig, collection: &str, id: &str, doc: &JsonValue) -> Result<Vec<u8>, OsunbitDBError> {
+    let plain = match encrypt_fields(config, collection, id, doc)? {
+        Some(sealed) => encode_plain(config, collection, &sealed)?,
+        None => encode_plain(config, collection, doc)?,
+    };
+    match config.collection(collection).and_then(|c| c.encryption) {
+        Some(cipher) => {
+            let provider = config.key_provider.as_deref().ok_or_else(|| {
+                OsunbitDBError::encryption(format!("{} is encrypted but no key provider is set", collection))
+            })?;
+            encryption::seal(cipher, provider, &doc_key(collection, id), &plain)
+        }
+        None => Ok(plain),
+    }
+}
+
+/// Codec, schema version and compression only.
+fn encode_plain(config: &DbConfig, collection: &str, doc: &JsonValue) -> Result<Vec<u8>, OsunbitDBError> {
+    let default = CollectionConfig::default();
+    let settings = config.collection(collection).unwrap_or(&default);
+    let mut bytes = settings.codec.encode(doc)?;
+    // Version 1 stays untagged, so collections without migrations keep their format
+    let version = settings.migrations.current();
+    if version > 1 {
+        let mut tagged = vec![VERSION_TAG];
+        tagged.extend_from_slice(&version.to_be_bytes());
+        tagged.extend(bytes);
+        bytes = tagged;
+    }
+    settings.compression.compress(bytes, settings.compress_min_bytes)
+}
+
+/// Encrypt the collection's encrypted fields, `None` when there are none to encrypt.
+fn encrypt_fields(config: &DbConfig, collection: &str, id: &str, doc: &JsonValue) -> Result<Option<JsonValue>, OsunbitDBError> {
+    let Some(rules) = config.collection(collection).map(|c| &c.encrypted_fields).filter(|r| !r.is_empty()) else {
+        return Ok(None);
+    };
+    let JsonValue::Object(obj) = doc else {
+        return Ok(None);
+    };
+    let provider = config.key_provider.as_deref().ok_or_else(|| {
+        OsunbitDBError::encryption(format!("{} has encrypted fields but no key provider is set", collection))
+    })?;
+
+    let mut obj = obj.clone();
+    for (path, mode) in rules {
+        if let Some(value) = get_deep(&obj, path) {
+            let sealed = encryption::encrypt_field(provider, *mode, collection, id, path, value)?;
+            set_deep(&mut obj, path, sealed);
+        }
+    }
+    Ok(Some(JsonValue::Object(obj)))
+}
+
+/// Inverse of `encode_doc`, also reads values written under other settings.
+/// Documents of an older schema version are migrated to the current one.
+pub(crate) fn decode_doc(config: &DbConfig, collection: &str, id: &str, bytes: &[u8]) -> Result<JsonValue, OsunbitDBError> {
+    let (version, mut doc) = decode_stored(config, &doc_key(collection, id), bytes)?;
+    // Without a key provider encrypted fields stay sealed and the rest of the
+    // document is still usable
+    if let Some(provider) = config.key_provider.as_deref() {
+        decrypt_fields(config, provider, collection, id, &mut doc)?;
+    }
+    match config.collection(collection) {
+        Some(settings) => settings.migrations.apply(version, doc),
+        None => Ok(doc),
+    }
+}
+
+/// Undo encryption, compression and the codec; encrypted fields stay sealed.
+/// Returns the schema version with the document. `key` is where it is stored.
+fn decode_stored(config: &DbConfig, key: &[u8], bytes: &[u8]) -> Result<(u32, JsonValue), OsunbitDBError> {
+    // Readers reassemble chunked values, a manifest left here means the chunks are broken
+    if chunk::is_manifest(bytes) {
+        return Err(OsunbitDBError::corruption("chunks of this value are missing or damaged".to_string()));
+    }
+    let opened;
+    let mut bytes = bytes;
+    if encryption::is_encrypted(bytes) {
+        opened = encryption::open(config.key_provider.as_deref(), key, bytes)?;
+        bytes = &opened;
+    }
+    let decompressed;
+    if Compression::is_compressed(bytes) {
+        decompressed = Compression::decompress(bytes)?;
+        bytes = &decompressed;
+    }
+    let (version, body) = split_version(bytes)?;
+    Ok((version, Codec::decode(body)?))
+}
+
+/// Strip the schema version header, untagged values are version 1.
+fn split_version(bytes: &[u8]) -> Result<(u32, &[u8]), OsunbitDBError> {
+    if bytes.first() != Some(&VERSION_TAG) {
+        return Ok((1, bytes));
+    }
+    let version = bytes
+        .get(1..5)
+        .and_then(|v| v.try_into().ok())
+        .map(u32::from_be_bytes)
+        .ok_or_else(|| OsunbitDBError::corruption("truncated schema version".to_string()))?;
+    Ok((version, &bytes[5..]))
+}
+
+/// Schema version a stored value was written with.
+pub(crate) fn stored_version(config: &DbConfig, collection: &str, id: &str, bytes: &[u8]) -> Result<u32, OsunbitDBError> {
+    Ok(decode_stored(config, &doc_key(collection, id), bytes)?.0)
+}
+
+/// Decrypt the `$enc:` values at the collection's encrypted and retired field
+/// paths. Strings elsewhere are user data, whatever they start with.
+fn decrypt_fields(
+    config: &DbConfig,
+    provider: &dyn KeyProvider,
+    collection: &str,
+    id: &str,
+    doc: &mut JsonValue,
+) -> Result<(), OsunbitDBError> {
+    let (Some(settings), JsonValue::Object(obj)) = (config.collection(collection), doc) else {
+        return Ok(());
+    };
+    for path in settings.field_paths() {
+        let plain = match get_deep(obj, path) {
+            Some(value) if encryption::field_info(value).is_some() => {
+                encryption::decrypt_field(provider, collection, id, path, value)?
+            }
+            _ => continue,
+        };
+        set_deep(obj, path, plain);
+    }
+    Ok(())
+}
+
+/// Stored form of `value` for a deterministic encrypted field, for equality
+/// lookups against the raw data.
+pub(crate) fn field_token(config: &DbConfig, collection: &str, path: &str, value: &JsonValue) -> Result<JsonValue, OsunbitDBError> {
+    let deterministic = config
+        .collection(collection)
+        .is_some_and(|c| c.encrypted_fields.iter().any(|(p, m)| p == path && *m == FieldEncryption::Deterministic));
+    if !deterministic {
+        return Err(OsunbitDBError::invalid_argument(format!(
+            "{} is not a deterministic encrypted field of {}", path, collection
+        )));
+    }
+    let provider = config
+        .key_provider
+        .as_deref()
+        .ok_or_else(|| OsunbitDBError::encryption("no key provider is set".to_string()))?;
+    // Deterministic fields are not bound to a document id
+    encryption::encrypt_field(provider, FieldEncryption::Deterministic, collection, "", path, value)
+}
+
+/// Whether a stored value already matches the collection's current settings:
+/// codec, compression, cipher, encrypted fields and current key id.
+pub(crate) fn is_current(config: &DbConfig, collection: &str, id: &str, bytes: &[u8]) -> Result<bool, OsunbitDBError> {
+    let key = doc_key(collection, id);
+    let cipher = config.collection(collection).and_then(|c| c.encryption);
+    let inner = match (cipher, encryption::is_encrypted(bytes)) {
+        (None, false) => bytes.to_vec(),
+        (Some(cipher), true) => {
+            let provider = config.key_provider.as_deref();
+            let current_key = provider.map(|p| p.current_key_id());
+            if encryption::cipher(bytes) != Some(cipher) || encryption::key_id(bytes) != current_key.as_deref() {
+                return Ok(false);
+            }
+            encryption::open(provider, &key, bytes)?
+        }
+        _ => return Ok(false),
+    };
+
+    let (_, stored) = decode_stored(config, &key, &inner)?;
+    let current_key = config.key_provider.as_deref().map(|p| p.current_key_id());
+    let default = CollectionConfig::default();
+    let settings = config.collection(collection).unwrap_or(&default);
+    if let JsonValue::Object(obj) = &stored {
+        for (path, mode) in &settings.encrypted_fields {
+            let Some(value) = get_deep(obj, path) else { continue };
+            match encryption::field_info(value) {
+                Some((m, key_id)) if m == *mode && Some(&key_id) == current_key.as_ref() => {}
+                _ => return Ok(false),
+            }
+        }
+        // Retired fields are stored in plaintext again once rewritten
+        if settings.retired_fields.iter().any(|path| get_deep(obj, path).and_then(encryption::field_info).is_some()) {
+            return Ok(false);
+        }
+    }
+    Ok(inner == encode_plain(config, collection, &stored)?)
+}
+
+/// JSON mirror that bincode can round-trip.
+#[derive(Serialize, Deserialize)]
+enum BinValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<BinValue>),
+    Object(Vec<(String, BinValue)>),
+}
+
+impl From<&JsonValue> for BinValue {
+    fn from(v: &JsonValue) -> Self {
+        match v {
+            JsonValue::Null => BinValue::Null,
+            JsonValue::Bool(b) => BinValue::Bool(*b),
+            JsonValue::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => BinValue::Int(i),
+                (None, Some(u)) => BinValue::UInt(u),
+                _ => BinValue::Float(n.as_f64().unwrap_or(0.0)),
+            },
+            JsonValue::String(s) => BinValue::String(s.clone()),
+            JsonValue::Array(items) => BinValue::Array(items.iter().map(BinValue::from).collect()),
+            JsonValue::Object(obj) => BinValue::Object(obj.iter().map(|(k, v)| (k.clone(), v.into())).collect()),
+        }
+    }
+}
+
+impl From<BinValue> for JsonValue {
+    fn from(v: BinValue) -> Self {
+        match v {
+            BinValue::Null => JsonValue::Null,
+            BinValue::Bool(b) => JsonValue::Bool(b),
+            BinValue::Int(i) => JsonValue::from(i),
+            BinValue::UInt(u) => JsonValue::from(u),
+            BinValue::Float(f) => Number::from_f64(f).map_or(JsonValue::Null, JsonValue::Number),
+            BinValue::String(s) => JsonValue::String(s),
+            BinValue::Array(items) => JsonValue::Array(items.into_iter().map(Into::into).collect()),
+            BinValue::Object(fields) => {
+                JsonValue::Object(fields.into_iter().map(|(k, v)| (k, v.into())).collect::<Map<_, _>>())
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::errors::OsunbitDBError;
+
+const LZ4_TAG: u8 = 0x10;
+const ZSTD_TAG: u8 = 0x11;
+
+/// Largest value `decompress` produces, far above any document a transaction
+/// can hold. Bounds what a corrupt size prefix or a compression bomb can
+/// make a read allocate.
+pub const MAX_DECOMPRESSED_BYTES: usize = 256 * 1024 * 1024;
+
+/// Compression of stored values, set per collection with
+/// `CollectionConfig::compression`.
+///
+/// Compressed values carry their own header byte, so turning compression on
+/// or off never breaks reading what is already stored. `Lz4` and `Zstd` need
+/// the `compression` feature, without it they fail with `OsunbitDBError::Codec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Fast, modest ratio.
+    Lz4,
+    /// Better ratio; level 1..=22, 3 is a good default.
+    Zstd(i32),
+}
+
+impl Compression {
+    /// Compress `bytes` when it pays off: values shorter than `min_bytes`, or
+    /// that would not shrink, are returned as-is.
+    pub fn compress(self, bytes: Vec<u8>, min_bytes: usize) -> Result<Vec<u8>, OsunbitDBError> {
+        if self == Compression::None || bytes.len() < min_bytes {
+            return Ok(bytes);
+        }
+        let packed = self.pack(&bytes)?;
+        Ok(if packed.len() < bytes.len() { packed } else { bytes })
+    }
+
+    /// Header byte followed by the compressed bytes.
+    #[cfg(feature = "compression")]
+    fn pack(self, bytes: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        Ok(match self {
+            Compression::None => bytes.to_vec(),
+            Compression::Lz4 => {
+                let mut out = vec![LZ4_TAG];
+                out.extend(lz4_flex::compress_prepend_size(bytes));
+                out
+            }
+            Compression::Zstd(level) => {
+                let mut out = vec![ZSTD_TAG];
+                out.extend(zstd::bulk::compress(bytes, level).map_err(|e| OsunbitDBError::codec(e.to_string()))?);
+                out
+            }
+        })
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn pack(self, _bytes: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        Err(disabled())
+    }
+
+    /// Undo `compress`; uncompressed values are returned unchanged. Output
+    /// above `MAX_DECOMPRESSED_BYTES` is treated as corruption.
+    pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        match bytes.first() {
+            #[cfg(not(feature = "compression"))]
+            Some(&LZ4_TAG | &ZSTD_TAG) => Err(disabled()),
+            #[cfg(feature = "compression")]
+            Some(&LZ4_TAG) => unpack_lz4(&bytes[1..]),
+            #[cfg(feature = "compression")]
+            Some(&ZSTD_TAG) => unpack_zstd(&bytes[1..]),
+            _ => Ok(bytes.to_vec()),
+        }
+    }
+
+    pub(crate) fn is_compressed(bytes: &[u8]) -> bool {
+        matches!(bytes.first(), Some(&LZ4_TAG | &ZSTD_TAG))
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn disabled() -> OsunbitDBError {
+    OsunbitDBError::codec("LZ4 and zstd need the `compression` feature")
+}
+
+/// LZ4 block with its little-endian u32 size prefix, checked before allocating.
+#[cfg(feature = "compression")]
+fn unpack_lz4(bytes: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+    let size = bytes
+        .get(..4)
+        .map(|p| u32::from_le_bytes(p.try_into().expect("4 bytes")) as usize)
+        .ok_or_else(|| OsunbitDBError::corruption("lz4: truncated size prefix"))?;
+    if size > MAX_DECOMPRESSED_BYTES {
+        return Err(OsunbitDBError::corruption(format!("lz4: size prefix {} exceeds {}", size, MAX_DECOMPRESSED_BYTES)));
+    }
+    let mut out = vec![0; size];
+    let written = lz4_flex::decompress_into(&bytes[4..], &mut out)
+        .map_err(|e| OsunbitDBError::corruption(format!("lz4: {}", e)))?;
+    if written != size {
+        return Err(OsunbitDBError::corruption(format!("lz4: expected {} bytes, got {}", size, written)));
+    }
+    Ok(out)
+}
+
+/// zstd frame, read through a decoder that stops past the cap.
+#[cfg(feature = "compression")]
+fn unpack_zstd(bytes: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+    use std::io::Read;
+    let decoder = zstd::stream::read::Decoder::new(bytes).map_err(|e| OsunbitDBError::corruption(format!("zstd: {}", e)))?;
+    let mut out = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_BYTES as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| OsunbitDBError::corruption(format!("zstd: {}", e)))?;
+    if out.len() > MAX_DECOMPRESSED_BYTES {
+        return Err(OsunbitDBError::corruption(format!("zstd: output exceeds {} bytes", MAX_DECOMPRESSED_BYTES)));
+    }
+    Ok(out)
+}
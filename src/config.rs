@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use crate::chunk::DEFAULT_CHUNK_SIZE;
+use crate::codec::Codec;
+use crate::compression::Compression;
+use crate::encryption::{Cipher, FieldEncryption, KeyProvider};
+use crate::errors::OsunbitDBError;
+use crate::ids::IdGenerator;
+use crate::migration::Migrations;
+#[cfg(feature = "schema")]
+use crate::schema::Schema;
+use crate::validation::Limits;
+
+/// Client-wide settings shared by `OsunbitDB` and every `TransactionHandle` it opens.
+/// Set through the `with_*` builders on `OsunbitDB`.
+#[derive(Clone, Default)]
+pub struct DbConfig {
+    /// HMAC-SHA256 key for signing pagination cursors, `None` leaves them unsigned.
+    pub(crate) cursor_key: Option<Vec<u8>>,
+    /// Per-collection settings keyed by exact name or `*` segment pattern.
+    pub(crate) collections: HashMap<String, CollectionConfig>,
+    /// Master keys for collections with encryption, also needed to read them.
+    pub(crate) key_provider: Option<Arc<dyn KeyProvider>>,
+    /// Values above this many bytes are split across keys, `None` is `DEFAULT_CHUNK_SIZE`.
+    pub(crate) chunk_size: Option<usize>,
+    /// Checks on names and documents at every entry point.
+    pub(crate) limits: Limits,
+}
+
+impl DbConfig {
+    pub(crate) fn chunk_size(&self) -> usize {
+        self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Settings for `collection`: an exact entry wins over a pattern like
+    /// `users:*:inbox`, where `*` matches one path segment.
+    pub(crate) fn collection(&self, collection: &str) -> Option<&CollectionConfig> {
+        if let Some(c) = self.collections.get(collection) {
+            return Some(c);
+        }
+        let segments: Vec<&str> = collection.split(':').collect();
+        self.collections
+            .iter()
+            .filter(|(pattern, _)| pattern.contains('*'))
+            .find(|(pattern, _)| {
+                let parts: Vec<&str> = pattern.split(':').collect();
+                parts.len() == segments.len()
+                    && parts.iter().zip(&segments).all(|(p, s)| *p == "*" || p == s)
+            })
+            .map(|(_, c)| c)
+    }
+}
+
+impl fmt::Debug for DbConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbConfig")
+            .field("cursor_key", &self.cursor_key.as_ref().map(|_| "<redacted>"))
+            .field("collections", &self.collections)
+            .field("key_provider", &self.key_provider.as_ref().map(|_| "<provider>"))
+            .field("chunk_size", &self.chunk_size)
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+/// Settings for one collection (or collection pattern), registered with
+/// `OsunbitDB::with_collection`.
+#[derive(Debug, Clone)]
+pub struct CollectionConfig {
+    pub(crate) id_generator: IdGenerator,
+    pub(crate) timestamps: bool,
+    pub(crate) codec: Codec,
+    pub(crate) compression: Compression,
+    pub(crate) compress_min_bytes: usize,
+    pub(crate) encryption: Option<Cipher>,
+    pub(crate) encrypted_fields: Vec<(String, FieldEncryption)>,
+    pub(crate) retired_fields: Vec<String>,
+    #[cfg(feature = "schema")]
+    pub(crate) schema: Option<Arc<Schema>>,
+    pub(crate) migrations: Migrations,
+}
+
+impl Default for CollectionConfig {
+    fn default() -> Self {
+        Self {
+            id_generator: IdGenerator::default(),
+            timestamps: false,
+            codec: Codec::default(),
+            compression: Compression::None,
+            compress_min_bytes: 256,
+            encryption: None,
+            encrypted_fields: Vec::new(),
+            retired_fields: Vec::new(),
+            #[cfg(feature = "schema")]
+            schema: None,
+            migrations: Migrations::default(),
+        }
+    }
+}
+
+impl CollectionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generator used by `add_auto` for this collection.
+    pub fn id_generator(mut self, generator: IdGenerator) -> Self {
+        self.id_generator = generator;
+        self
+    }
+
+    /// Stamp `createdAt` on the first write and `updatedAt` on every write.
+    /// Both come from the transaction's start timestamp, so every document of
+    /// one commit shares the same value (ms since epoch).
+    pub fn timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Format for new writes; values written with another codec stay readable.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Compress values of at least `min_bytes` (encoded size, default 256);
+    /// smaller values are stored as-is.
+    pub fn compression(mut self, compression: Compression, min_bytes: usize) -> Self {
+        self.compression = compression;
+        self.compress_min_bytes = min_bytes;
+        self
+    }
+
+    /// Encrypt values client-side with keys from `OsunbitDB::with_key_provider`.
+    /// Applied after compression.
+    pub fn encryption(mut self, cipher: Cipher) -> Self {
+        self.encryption = Some(cipher);
+        self
+    }
+
+    /// Encrypt the field at `path` (dot notation) inside the document, leaving
+    /// the other fields readable. Uses the key provider like `encryption`.
+    /// Reads only decrypt the paths registered here or with `retired_field`.
+    pub fn encrypt_field(mut self, path: &str, mode: FieldEncryption) -> Self {
+        self.encrypted_fields.retain(|(p, _)| p != path);
+        self.encrypted_fields.push((path.to_string(), mode));
+        self
+    }
+
+    /// Keep decrypting `path` on reads after its `encrypt_field` rule is
+    /// dropped; new writes and `OsunbitDB::rewrite` store it in plaintext.
+    pub fn retired_field(mut self, path: &str) -> Self {
+        if !self.retired_fields.iter().any(|p| p == path) {
+            self.retired_fields.push(path.to_string());
+        }
+        self
+    }
+
+    /// Paths that may hold encrypted field values.
+    pub(crate) fn field_paths(&self) -> impl Iterator<Item = &str> {
+        self.encrypted_fields.iter().map(|(p, _)| p.as_str()).chain(self.retired_fields.iter().map(String::as_str))
+    }
+
+    /// Reject writes whose resulting document does not match `schema`
+    /// (checked after updates are merged and timestamps are stamped).
+    /// Needs the `schema` feature.
+    #[cfg(feature = "schema")]
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(Arc::new(schema));
+        self
+    }
+
+    /// Register the next migration step: the first call migrates version 1
+    /// (any document written before) to 2, the next 2 to 3, and so on.
+    ///
+    /// Reads apply the missing steps lazily, the next write stores the result
+    /// under the current version; `OsunbitDB::migrate` does it eagerly.
+    pub fn migration<F>(mut self, step: F) -> Self
+    where
+        F: Fn(serde_json::Value) -> Result<serde_json::Value, OsunbitDBError> + Send + Sync + 'static,
+    {
+        self.migrations.push(Arc::new(step));
+        self
+    }
+
+    /// Version documents of this collection are written with.
+    pub fn schema_version(&self) -> u32 {
+        self.migrations.current()
+    }
+}
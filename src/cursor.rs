@@ -0,0 +1,120 @@
+//! Opaque pagination cursors.
+//!
+//! A token is `base64url(version | bincode(payload) | hmac?)`. It is opaque to
+//! clients but not encrypted; configure a cursor key so tokens are signed and
+//! any modification is rejected.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::errors::OsunbitDBError;
+use crate::filter::Filter;
+use crate::scan::Direction;
+
+const VERSION_UNSIGNED: u8 = 1;
+const VERSION_SIGNED: u8 = 2;
+const MAC_LEN: usize = 32;
+
+/// Decoded content of a cursor token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub descending: bool,
+    /// Last document id scanned, the next page starts after it.
+    pub last_id: String,
+    pub collection_hash: u64,
+    pub filter_hash: u64,
+    /// TiKV timestamp the page was read at.
+    pub snapshot_ts: u64,
+}
+
+impl Cursor {
+    pub(crate) fn new(collection: &str, direction: Direction, last_id: &str, filter: Option<&Filter>, snapshot_ts: u64) -> Self {
+        Self {
+            descending: direction == Direction::Descending,
+            last_id: last_id.to_string(),
+            collection_hash: hash64(collection.as_bytes()),
+            filter_hash: filter_hash(filter),
+            snapshot_ts,
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        if self.descending { Direction::Descending } else { Direction::Ascending }
+    }
+
+    pub fn encode(&self, key: Option<&[u8]>) -> Result<String, OsunbitDBError> {
+        let payload = bincode::serialize(self)?;
+        let mut raw = Vec::with_capacity(1 + payload.len() + MAC_LEN);
+        match key {
+            Some(key) => {
+                raw.push(VERSION_SIGNED);
+                raw.extend_from_slice(&payload);
+                let mut mac = hmac(key);
+                mac.update(&raw);
+                raw.extend_from_slice(&mac.finalize().into_bytes());
+            }
+            None => {
+                raw.push(VERSION_UNSIGNED);
+                raw.extend_from_slice(&payload);
+            }
+        }
+        Ok(URL_SAFE_NO_PAD.encode(raw))
+    }
+
+    /// Decode a token. With a key, only correctly signed tokens are accepted.
+    pub fn decode(token: &str, key: Option<&[u8]>) -> Result<Self, OsunbitDBError> {
+        let invalid = |why: &str| OsunbitDBError::invalid_argument(format!("invalid cursor: {}", why));
+        let raw = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid("not base64"))?;
+        let (version, rest) = raw.split_first().ok_or_else(|| invalid("empty"))?;
+
+        let payload = match (*version, key) {
+            (VERSION_SIGNED, Some(key)) => {
+                if rest.len() < MAC_LEN {
+                    return Err(invalid("truncated"));
+                }
+                let (payload, tag) = rest.split_at(rest.len() - MAC_LEN);
+                let mut mac = hmac(key);
+                mac.update(&raw[..1 + payload.len()]);
+                mac.verify_slice(tag).map_err(|_| invalid("signature mismatch"))?;
+                payload
+            }
+            (VERSION_UNSIGNED, None) => rest,
+            (VERSION_UNSIGNED, Some(_)) => return Err(invalid("unsigned cursor")),
+            (VERSION_SIGNED, None) => return Err(invalid("signed cursor but no cursor key configured")),
+            _ => return Err(invalid("unknown version")),
+        };
+
+        bincode::deserialize(payload).map_err(|_| invalid("malformed payload"))
+    }
+
+    /// Reject cursors issued for another collection, filter or direction.
+    pub(crate) fn check(&self, collection: &str, direction: Direction, filter: Option<&Filter>) -> Result<(), OsunbitDBError> {
+        if self.collection_hash != hash64(collection.as_bytes())
+            || self.filter_hash != filter_hash(filter)
+            || self.direction() != direction
+        {
+            return Err(OsunbitDBError::invalid_argument(
+                "cursor does not belong to this query".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn hmac(key: &[u8]) -> Hmac<Sha256> {
+    <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length")
+}
+
+fn hash64(bytes: &[u8]) -> u64 {
+    let digest = Sha256::digest(bytes);
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+fn filter_hash(filter: Option<&Filter>) -> u64 {
+    match filter {
+        Some(f) => hash64(&serde_json::to_vec(f).unwrap_or_default()),
+        None => 0,
+    }
+}
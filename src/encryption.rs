@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "encryption")]
+use aes_gcm::Aes256Gcm;
+#[cfg(feature = "encryption")]
+use chacha20poly1305::ChaCha20Poly1305;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde_json::Value as JsonValue;
+use sha2::Sha256;
+use crate::errors::OsunbitDBError;
+
+/// Control byte no other header uses; legacy JSON may start with whitespace
+/// (0x20 included), so printable bytes are out.
+const ENCRYPTED_TAG: u8 = 0x12;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+/// Data key plus AEAD tag.
+const WRAPPED_KEY_LEN: usize = KEY_LEN + 16;
+
+/// AEAD used for a collection's values, set with `CollectionConfig::encryption`.
+/// Needs the `encryption` feature (as do encrypted fields), without it every
+/// encrypt or decrypt fails with `OsunbitDBError::Encryption`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn id(self) -> u8 {
+        match self {
+            Cipher::Aes256Gcm => 1,
+            Cipher::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Cipher::Aes256Gcm),
+            2 => Some(Cipher::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    fn seal(self, key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], plain: &[u8], aad: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        let payload = Payload { msg: plain, aad };
+        let out = match self {
+            Cipher::Aes256Gcm => Aes256Gcm::new(key.into()).encrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305 => ChaCha20Poly1305::new(key.into()).encrypt(nonce.into(), payload),
+        };
+        out.map_err(|_| OsunbitDBError::encryption("encryption failed".to_string()))
+    }
+
+    #[cfg(feature = "encryption")]
+    fn open(self, key: &[u8; KEY_LEN], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        let payload = Payload { msg: sealed, aad };
+        let out = match self {
+            Cipher::Aes256Gcm => Aes256Gcm::new(key.into()).decrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305 => ChaCha20Poly1305::new(key.into()).decrypt(nonce.into(), payload),
+        };
+        out.map_err(|_| OsunbitDBError::encryption("decryption failed (wrong key or tampered value)".to_string()))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn seal(self, _key: &[u8; KEY_LEN], _nonce: &[u8; NONCE_LEN], _plain: &[u8], _aad: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        Err(OsunbitDBError::encryption("encryption needs the `encryption` feature"))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn open(self, _key: &[u8; KEY_LEN], _nonce: &[u8], _sealed: &[u8], _aad: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+        Err(OsunbitDBError::encryption("decryption needs the `encryption` feature"))
+    }
+}
+
+/// Source of master keys. Each value is encrypted with a fresh data key, and
+/// only that data key is encrypted with the master key named by
+/// `current_key_id`, so rotating means adding a key and making it current.
+pub trait KeyProvider: Send + Sync {
+    /// Key id new values are written under (at most 255 bytes).
+    fn current_key_id(&self) -> String;
+    /// 256-bit master key for `key_id`, `None` if unknown.
+    fn key(&self, key_id: &str) -> Option<[u8; KEY_LEN]>;
+}
+
+/// In-memory `KeyProvider`, e.g. for keys loaded from the environment.
+#[derive(Clone)]
+pub struct StaticKeys {
+    current: String,
+    keys: HashMap<String, [u8; KEY_LEN]>,
+}
+
+impl StaticKeys {
+    pub fn new(key_id: &str, key: [u8; KEY_LEN]) -> Self {
+        Self { current: key_id.to_string(), keys: HashMap::from([(key_id.to_string(), key)]) }
+    }
+
+    /// Add a key that is still readable but not used for new writes.
+    pub fn with_key(mut self, key_id: &str, key: [u8; KEY_LEN]) -> Self {
+        self.keys.insert(key_id.to_string(), key);
+        self
+    }
+
+    /// Add a key and write new values under it.
+    pub fn rotate(mut self, key_id: &str, key: [u8; KEY_LEN]) -> Self {
+        self.keys.insert(key_id.to_string(), key);
+        self.current = key_id.to_string();
+        self
+    }
+}
+
+impl fmt::Debug for StaticKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<&String> = self.keys.keys().collect();
+        ids.sort();
+        f.debug_struct("StaticKeys").field("current", &self.current).field("key_ids", &ids).finish()
+    }
+}
+
+impl KeyProvider for StaticKeys {
+    fn current_key_id(&self) -> String {
+        self.current.clone()
+    }
+
+    fn key(&self, key_id: &str) -> Option<[u8; KEY_LEN]> {
+        self.keys.get(key_id).copied()
+    }
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut out = [0u8; N];
+    rand::rngs::OsRng.fill_bytes(&mut out);
+    out
+}
+
+/// Envelope-encrypt `plain`, stored at `key`:
+/// `tag | cipher | key id len | key id | key nonce | wrapped data key | nonce | ciphertext`.
+/// The header up to the key id and `key` are authenticated with both layers,
+/// so a value copied to another key no longer opens.
+pub(crate) fn seal(cipher: Cipher, provider: &dyn KeyProvider, key: &[u8], plain: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+    let key_id = provider.current_key_id();
+    let master = provider
+        .key(&key_id)
+        .ok_or_else(|| OsunbitDBError::encryption(format!("unknown key id {:?}", key_id)))?;
+    let id_len = u8::try_from(key_id.len())
+        .map_err(|_| OsunbitDBError::encryption("key id longer than 255 bytes".to_string()))?;
+
+    let mut out = vec![ENCRYPTED_TAG, cipher.id(), id_len];
+    out.extend_from_slice(key_id.as_bytes());
+    let aad = [&out[..], key].concat();
+
+    let data_key = random::<KEY_LEN>();
+    let key_nonce = random::<NONCE_LEN>();
+    let wrapped = cipher.seal(&master, &key_nonce, &data_key, &aad)?;
+    let nonce = random::<NONCE_LEN>();
+    let sealed = cipher.seal(&data_key, &nonce, plain, &aad)?;
+
+    out.extend_from_slice(&key_nonce);
+    out.extend(wrapped);
+    out.extend_from_slice(&nonce);
+    out.extend(sealed);
+    Ok(out)
+}
+
+pub(crate) fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.first() == Some(&ENCRYPTED_TAG)
+}
+
+pub(crate) fn cipher(bytes: &[u8]) -> Option<Cipher> {
+    if !is_encrypted(bytes) {
+        return None;
+    }
+    Cipher::from_id(*bytes.get(1)?)
+}
+
+/// Key id an encrypted value was written under.
+pub(crate) fn key_id(bytes: &[u8]) -> Option<&str> {
+    if !is_encrypted(bytes) {
+        return None;
+    }
+    let len = *bytes.get(2)? as usize;
+    std::str::from_utf8(bytes.get(3..3 + len)?).ok()
+}
+
+/// Inverse of `seal`; `key` must be the key the value was sealed for.
+pub(crate) fn open(provider: Option<&dyn KeyProvider>, key: &[u8], bytes: &[u8]) -> Result<Vec<u8>, OsunbitDBError> {
+    let corrupt = || OsunbitDBError::encryption("truncated encrypted value".to_string());
+    let cipher = bytes
+        .get(1)
+        .and_then(|id| Cipher::from_id(*id))
+        .ok_or_else(|| OsunbitDBError::encryption("unknown cipher".to_string()))?;
+    let key_id = key_id(bytes).ok_or_else(corrupt)?;
+    let header_len = 3 + key_id.len();
+
+    let provider = provider
+        .ok_or_else(|| OsunbitDBError::encryption("value is encrypted but no key provider is set".to_string()))?;
+    let master = provider
+        .key(key_id)
+        .ok_or_else(|| OsunbitDBError::encryption(format!("unknown key id {:?}", key_id)))?;
+
+    let aad = [&bytes[..header_len], key].concat();
+    let rest = &bytes[header_len..];
+    if rest.len() < NONCE_LEN + WRAPPED_KEY_LEN + NONCE_LEN {
+        return Err(corrupt());
+    }
+    let (key_nonce, rest) = rest.split_at(NONCE_LEN);
+    let (wrapped, rest) = rest.split_at(WRAPPED_KEY_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+    let data_key: [u8; KEY_LEN] = cipher
+        .open(&master, key_nonce, wrapped, &aad)?
+        .try_into()
+        .map_err(|_| corrupt())?;
+    cipher.open(&data_key, nonce, sealed, &aad)
+}
+
+/// Prefix of encrypted field values inside a document.
+pub const FIELD_PREFIX: &str = "$enc:";
+
+/// How a single field is encrypted, set with `CollectionConfig::encrypt_field`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldEncryption {
+    /// Fresh nonce per write; equal values give different ciphertexts.
+    Randomized,
+    /// Nonce derived from the value, so equal values under the same key give
+    /// the same ciphertext and can be matched or indexed by equality. Reveals
+    /// which documents share a value.
+    Deterministic,
+}
+
+impl FieldEncryption {
+    fn id(self) -> u8 {
+        match self {
+            FieldEncryption::Randomized => 1,
+            FieldEncryption::Deterministic => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(FieldEncryption::Randomized),
+            2 => Some(FieldEncryption::Deterministic),
+            _ => None,
+        }
+    }
+}
+
+/// Separate keys per purpose, derived from the master key.
+fn subkey(master: &[u8; KEY_LEN], label: &str) -> [u8; KEY_LEN] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(master).expect("HMAC accepts any key length");
+    mac.update(label.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Authenticated data of an encrypted field: its header, path and collection,
+/// plus the document id for randomized fields. Deterministic fields cannot bind
+/// the id, equal values must match across documents for `field_token` lookups.
+fn field_aad(header: &[u8], mode: FieldEncryption, collection: &str, id: &str, path: &str) -> Vec<u8> {
+    let mut aad = [header, path.as_bytes(), &[0], collection.as_bytes()].concat();
+    if mode == FieldEncryption::Randomized {
+        aad.push(b':');
+        aad.extend_from_slice(id.as_bytes());
+    }
+    aad
+}
+
+/// Encrypt one field value of `collection/id` into
+/// `"$enc:" + base64url(mode | key id len | key id | nonce | ciphertext)`.
+/// See `field_aad` for what a ciphertext is bound to.
+pub(crate) fn encrypt_field(
+    provider: &dyn KeyProvider,
+    mode: FieldEncryption,
+    collection: &str,
+    id: &str,
+    path: &str,
+    value: &JsonValue,
+) -> Result<JsonValue, OsunbitDBError> {
+    let key_id = provider.current_key_id();
+    let master = provider
+        .key(&key_id)
+        .ok_or_else(|| OsunbitDBError::encryption(format!("unknown key id {:?}", key_id)))?;
+    let id_len = u8::try_from(key_id.len())
+        .map_err(|_| OsunbitDBError::encryption("key id longer than 255 bytes".to_string()))?;
+    let plain = serde_json::to_vec(value)?;
+
+    let nonce: [u8; NONCE_LEN] = match mode {
+        FieldEncryption::Randomized => random(),
+        FieldEncryption::Deterministic => {
+            let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&subkey(&master, "osunbit field nonce"))
+                .expect("HMAC accepts any key length");
+            mac.update(path.as_bytes());
+            mac.update(&[0]);
+            mac.update(&plain);
+            mac.finalize().into_bytes()[..NONCE_LEN].try_into().expect("HMAC output is 32 bytes")
+        }
+    };
+
+    let mut raw = vec![mode.id(), id_len];
+    raw.extend_from_slice(key_id.as_bytes());
+    let aad = field_aad(&raw, mode, collection, id, path);
+    let sealed = Cipher::Aes256Gcm.seal(&subkey(&master, "osunbit field key"), &nonce, &plain, &aad)?;
+    raw.extend_from_slice(&nonce);
+    raw.extend(sealed);
+    Ok(JsonValue::String(format!("{}{}", FIELD_PREFIX, URL_SAFE_NO_PAD.encode(raw))))
+}
+
+/// Mode and key id of an encrypted field value, `None` if it is not one.
+pub(crate) fn field_info(value: &JsonValue) -> Option<(FieldEncryption, String)> {
+    let raw = URL_SAFE_NO_PAD.decode(value.as_str()?.strip_prefix(FIELD_PREFIX)?).ok()?;
+    let mode = FieldEncryption::from_id(*raw.first()?)?;
+    let len = *raw.get(1)? as usize;
+    let key_id = std::str::from_utf8(raw.get(2..2 + len)?).ok()?;
+    Some((mode, key_id.to_string()))
+}
+
+/// Inverse of `encrypt_field`; values without the prefix are returned as-is.
+pub(crate) fn decrypt_field(
+    provider: &dyn KeyProvider,
+    collection: &str,
+    id: &str,
+    path: &str,
+    value: &JsonValue,
+) -> Result<JsonValue, OsunbitDBError> {
+    let Some(token) = value.as_str().and_then(|s| s.strip_prefix(FIELD_PREFIX)) else {
+        return Ok(value.clone());
+    };
+    let corrupt = || OsunbitDBError::encryption(format!("malformed encrypted field {}", path));
+    let raw = URL_SAFE_NO_PAD.decode(token).map_err(|_| corrupt())?;
+    let (mode, key_id) = field_info(value).ok_or_else(corrupt)?;
+    let header_len = 2 + key_id.len();
+    if raw.len() < header_len + NONCE_LEN {
+        return Err(corrupt());
+    }
+
+    let master = provider
+        .key(&key_id)
+        .ok_or_else(|| OsunbitDBError::encryption(format!("unknown key id {:?}", key_id)))?;
+
+    let aad = field_aad(&raw[..header_len], mode, collection, id, path);
+    let (nonce, sealed) = raw[header_len..].split_at(NONCE_LEN);
+    let plain = Cipher::Aes256Gcm.open(&subkey(&master, "osunbit field key"), nonce, sealed, &aad)?;
+    Ok(serde_json::from_slice(&plain)?)
+}
@@ -1,22 +1,313 @@
+use std::fmt;
+use std::io;
 use thiserror::Error;
 use tikv_rust::Error as TiKVError;
 use tokio::task::JoinError;
 use bincode;
+use crate::validation::Violation;
 
+/// gRPC status codes TiKV and PD answer with.
+const GRPC_DEADLINE_EXCEEDED: i32 = 4;
+const GRPC_UNAVAILABLE: i32 = 14;
+
+/// Every variant that can come out of a database call carries the
+/// `ErrorContext` of that call. New variants may be added in minor releases.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum OsunbitDBError {
-    #[error("TiKV client error: {0}")]
-    TiKV(#[from] TiKVError),
+    /// Any other TiKV client error; not retryable.
+    #[error("TiKV client error: {source}{}", at(.context))]
+    TiKV { source: Box<TiKVError>, context: Option<ErrorContext> },
+
+    /// Another transaction wrote or locked the same keys, or a deadlock was
+    /// broken. Retrying the whole transaction usually succeeds.
+    #[error("Write conflict: {source}{}", at(.context))]
+    WriteConflict { source: Box<TiKVError>, context: Option<ErrorContext> },
+
+    /// A TiKV or PD request ran out of time.
+    #[error("Request timed out: {source}{}", at(.context))]
+    Timeout { source: Box<TiKVError>, context: Option<ErrorContext> },
+
+    /// The region (or its store) could not serve the request: leader change,
+    /// split, busy or unreachable store.
+    #[error("Region unavailable: {source}{}", at(.context))]
+    RegionUnavailable { source: Box<TiKVError>, context: Option<ErrorContext> },
+
+    #[error("Serialization error: {source}{}", at(.context))]
+    Bincode { source: Box<bincode::ErrorKind>, context: Option<ErrorContext> },
+
+    #[error("Join error: {source}{}", at(.context))]
+    Join { source: JoinError, context: Option<ErrorContext> },
+
+    #[error("Serde JSON error: {source}{}", at(.context))]
+    SerdeJsonError { source: serde_json::Error, context: Option<ErrorContext> },
+
+    #[error("Invalid update: {reason}{}", at(.context))]
+    InvalidUpdate { reason: String, context: Option<ErrorContext> },
+
+    #[error("Invalid argument: {reason}{}", at(.context))]
+    InvalidArgument { reason: String, context: Option<ErrorContext> },
+
+    #[error("Document {collection}/{id} not found{}", at(.context))]
+    NotFound { collection: String, id: String, context: Option<ErrorContext> },
+
+    #[error("Document {collection}/{id} already exists{}", at(.context))]
+    AlreadyExists { collection: String, id: String, context: Option<ErrorContext> },
+
+    /// A value could not be encoded.
+    #[error("Codec error: {reason}{}", at(.context))]
+    Codec { reason: String, context: Option<ErrorContext> },
+
+    /// Stored bytes could not be read back: bad header, failed decompression,
+    /// missing chunks or a checksum mismatch.
+    #[error("Corrupt data: {reason}{}", at(.context))]
+    Corruption { reason: String, context: Option<ErrorContext> },
+
+    #[error("Encryption error: {reason}{}", at(.context))]
+    Encryption { reason: String, context: Option<ErrorContext> },
+
+    #[error("Invalid collection name {collection:?}: {reason}")]
+    InvalidCollection { collection: String, reason: String },
+
+    #[error("Invalid document id {id:?}: {reason}")]
+    InvalidId { id: String, reason: String },
+
+    #[error("{0:?} is a reserved name")]
+    ReservedName(String),
+
+    #[error("Document is {size} bytes, the limit is {max}{}", at(.context))]
+    DocumentTooLarge { size: usize, max: usize, context: Option<ErrorContext> },
+
+    #[error("Document nests deeper than {max} levels{}", at(.context))]
+    DocumentTooDeep { max: usize, context: Option<ErrorContext> },
+
+    /// Document rejected by its collection's schema, with every violation.
+    #[error("Validation failed: {}", join(.0))]
+    Validation(Vec<Violation>),
+}
+
+/// Operation, collection and id of the call an error came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub operation: &'static str,
+    pub collection: Option<String>,
+    pub id: Option<String>,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.operation)?;
+        match (&self.collection, &self.id) {
+            (Some(c), Some(id)) => write!(f, " {}/{}", c, id),
+            (Some(c), None) => write!(f, " {}", c),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl OsunbitDBError {
+    pub(crate) fn corruption(reason: impl Into<String>) -> Self {
+        OsunbitDBError::Corruption { reason: reason.into(), context: None }
+    }
 
-    #[error("Serialization error: {0}")]
-    Bincode(#[from] Box<bincode::ErrorKind>),
+    pub(crate) fn invalid_update(reason: impl Into<String>) -> Self {
+        OsunbitDBError::InvalidUpdate { reason: reason.into(), context: None }
+    }
 
-    #[error("Join error: {0}")]
-    Join(#[from] JoinError),
+    pub(crate) fn invalid_argument(reason: impl Into<String>) -> Self {
+        OsunbitDBError::InvalidArgument { reason: reason.into(), context: None }
+    }
 
-    #[error("Serde JSON error: {0}")]
-    SerdeJsonError(#[from] serde_json::Error),
+    pub(crate) fn codec(reason: impl Into<String>) -> Self {
+        OsunbitDBError::Codec { reason: reason.into(), context: None }
+    }
+
+    pub(crate) fn encryption(reason: impl Into<String>) -> Self {
+        OsunbitDBError::Encryption { reason: reason.into(), context: None }
+    }
+
+    /// Record the call an error came from. An outer call replaces the context
+    /// of an inner one, so it is always the method the caller invoked, but
+    /// keeps the inner id when it has none, e.g. the failing item of a batch.
+    /// Errors that already name what they are about (invalid names, schema
+    /// violations with their ids) carry no context.
+    pub(crate) fn with_context(mut self, operation: &'static str, collection: &str, id: Option<&str>) -> Self {
+        if let Some(slot) = self.context_mut() {
+            let collection = (!collection.is_empty()).then(|| collection.to_string());
+            let id = id
+                .map(str::to_string)
+                .or_else(|| slot.take().filter(|inner| inner.collection == collection).and_then(|inner| inner.id));
+            *slot = Some(ErrorContext { operation, collection, id });
+        }
+        self
+    }
+
+    fn context_mut(&mut self) -> Option<&mut Option<ErrorContext>> {
+        match self {
+            OsunbitDBError::TiKV { context, .. }
+            | OsunbitDBError::WriteConflict { context, .. }
+            | OsunbitDBError::Timeout { context, .. }
+            | OsunbitDBError::RegionUnavailable { context, .. }
+            | OsunbitDBError::Bincode { context, .. }
+            | OsunbitDBError::Join { context, .. }
+            | OsunbitDBError::SerdeJsonError { context, .. }
+            | OsunbitDBError::InvalidUpdate { context, .. }
+            | OsunbitDBError::InvalidArgument { context, .. }
+            | OsunbitDBError::NotFound { context, .. }
+            | OsunbitDBError::AlreadyExists { context, .. }
+            | OsunbitDBError::Codec { context, .. }
+            | OsunbitDBError::Corruption { context, .. }
+            | OsunbitDBError::Encryption { context, .. }
+            | OsunbitDBError::DocumentTooLarge { context, .. }
+            | OsunbitDBError::DocumentTooDeep { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The call an error came from, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            OsunbitDBError::TiKV { context, .. }
+            | OsunbitDBError::WriteConflict { context, .. }
+            | OsunbitDBError::Timeout { context, .. }
+            | OsunbitDBError::RegionUnavailable { context, .. }
+            | OsunbitDBError::Bincode { context, .. }
+            | OsunbitDBError::Join { context, .. }
+            | OsunbitDBError::SerdeJsonError { context, .. }
+            | OsunbitDBError::InvalidUpdate { context, .. }
+            | OsunbitDBError::InvalidArgument { context, .. }
+            | OsunbitDBError::NotFound { context, .. }
+            | OsunbitDBError::AlreadyExists { context, .. }
+            | OsunbitDBError::Codec { context, .. }
+            | OsunbitDBError::Corruption { context, .. }
+            | OsunbitDBError::Encryption { context, .. }
+            | OsunbitDBError::DocumentTooLarge { context, .. }
+            | OsunbitDBError::DocumentTooDeep { context, .. } => context.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Stable machine-readable code, one per variant; safe to log, alert or
+    /// branch on across versions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            OsunbitDBError::TiKV { .. } => "tikv",
+            OsunbitDBError::WriteConflict { .. } => "write_conflict",
+            OsunbitDBError::Timeout { .. } => "timeout",
+            OsunbitDBError::RegionUnavailable { .. } => "region_unavailable",
+            OsunbitDBError::Bincode { .. } => "serialization",
+            OsunbitDBError::Join { .. } => "join",
+            OsunbitDBError::SerdeJsonError { .. } => "json",
+            OsunbitDBError::InvalidUpdate { .. } => "invalid_update",
+            OsunbitDBError::InvalidArgument { .. } => "invalid_argument",
+            OsunbitDBError::NotFound { .. } => "not_found",
+            OsunbitDBError::AlreadyExists { .. } => "already_exists",
+            OsunbitDBError::Codec { .. } => "codec",
+            OsunbitDBError::Corruption { .. } => "corruption",
+            OsunbitDBError::Encryption { .. } => "encryption",
+            OsunbitDBError::InvalidCollection { .. } => "invalid_collection",
+            OsunbitDBError::InvalidId { .. } => "invalid_id",
+            OsunbitDBError::ReservedName(_) => "reserved_name",
+            OsunbitDBError::DocumentTooLarge { .. } => "document_too_large",
+            OsunbitDBError::DocumentTooDeep { .. } => "document_too_deep",
+            OsunbitDBError::Validation(_) => "validation",
+        }
+    }
+
+    /// Whether running the same transaction again may succeed: conflicts,
+    /// timeouts and unavailable regions. Everything else fails the same way
+    /// on retry.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            OsunbitDBError::WriteConflict { .. } | OsunbitDBError::Timeout { .. } | OsunbitDBError::RegionUnavailable { .. }
+        )
+    }
+}
+
+impl From<Box<bincode::ErrorKind>> for OsunbitDBError {
+    fn from(source: Box<bincode::ErrorKind>) -> Self {
+        OsunbitDBError::Bincode { source, context: None }
+    }
+}
+
+impl From<JoinError> for OsunbitDBError {
+    fn from(source: JoinError) -> Self {
+        OsunbitDBError::Join { source, context: None }
+    }
+}
+
+impl From<serde_json::Error> for OsunbitDBError {
+    fn from(source: serde_json::Error) -> Self {
+        OsunbitDBError::SerdeJsonError { source, context: None }
+    }
+}
+
+impl From<TiKVError> for OsunbitDBError {
+    fn from(e: TiKVError) -> Self {
+        let (source, context) = (Box::new(e), None);
+        match classify(&source) {
+            Some(TiKVKind::Conflict) => OsunbitDBError::WriteConflict { source, context },
+            Some(TiKVKind::Timeout) => OsunbitDBError::Timeout { source, context },
+            Some(TiKVKind::Unavailable) => OsunbitDBError::RegionUnavailable { source, context },
+            None => OsunbitDBError::TiKV { source, context },
+        }
+    }
+}
+
+/// Retryable classes of TiKV errors.
+enum TiKVKind {
+    Conflict,
+    Timeout,
+    Unavailable,
+}
+
+/// Retryable class of a TiKV error, `None` for everything else.
+fn classify(e: &TiKVError) -> Option<TiKVKind> {
+    match e {
+        TiKVError::KeyError(key) => {
+            let conflict = key.conflict.is_some() || key.deadlock.is_some() || key.locked.is_some() || !key.retryable.is_empty();
+            conflict.then_some(TiKVKind::Conflict)
+        }
+        // Too large or a full disk will not heal by retrying
+        TiKVError::RegionError(region) => {
+            (region.raft_entry_too_large.is_none() && region.disk_full.is_none()).then_some(TiKVKind::Unavailable)
+        }
+        TiKVError::LeaderNotFound { .. }
+        | TiKVError::RegionForKeyNotFound { .. }
+        | TiKVError::RegionForRangeNotFound { .. }
+        | TiKVError::RegionNotFoundInResponse { .. }
+        | TiKVError::EntryNotFoundInRegionCache
+        | TiKVError::NoCurrentRegions
+        | TiKVError::Grpc(_) => Some(TiKVKind::Unavailable),
+        TiKVError::GrpcAPI(status) => match i32::from(status.code()) {
+            GRPC_DEADLINE_EXCEEDED => Some(TiKVKind::Timeout),
+            GRPC_UNAVAILABLE => Some(TiKVKind::Unavailable),
+            _ => None,
+        },
+        TiKVError::Io(io) if io.kind() == io::ErrorKind::TimedOut => Some(TiKVKind::Timeout),
+        TiKVError::PessimisticLockError { inner, .. } => classify(inner),
+        TiKVError::MultipleKeyErrors(errors) | TiKVError::ExtractedErrors(errors) => errors.iter().find_map(classify),
+        _ => None,
+    }
+}
+
+/// Attach an `ErrorContext` to the error of a result.
+pub(crate) trait ResultExt<T> {
+    fn with_context(self, operation: &'static str, collection: &str, id: Option<&str>) -> Result<T, OsunbitDBError>;
+}
+
+impl<T> ResultExt<T> for Result<T, OsunbitDBError> {
+    fn with_context(self, operation: &'static str, collection: &str, id: Option<&str>) -> Result<T, OsunbitDBError> {
+        self.map_err(|e| e.with_context(operation, collection, id))
+    }
+}
+
+/// ` (in <context>)` for messages, empty without one.
+fn at(context: &Option<ErrorContext>) -> String {
+    context.as_ref().map_or(String::new(), |c| format!(" (in {})", c))
+}
 
-    #[error("Invalid update: {0}")]
-    InvalidUpdate(String),
+fn join(violations: &[Violation]) -> String {
+    violations.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; ")
 }
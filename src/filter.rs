@@ -0,0 +1,135 @@
+use serde::Serialize;
+use serde_json::{Value as JsonValue, Map};
+use std::cmp::Ordering;
+use crate::utils::get_deep;
+
+/// Document predicate on dot-notation paths, evaluated client-side.
+///
+/// `Filter::eq("status", "pending").and(Filter::gt("amount", 100))`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Filter {
+    Eq(String, JsonValue),
+    Ne(String, JsonValue),
+    Gt(String, JsonValue),
+    Gte(String, JsonValue),
+    Lt(String, JsonValue),
+    Lte(String, JsonValue),
+    In(String, Vec<JsonValue>),
+    /// Array field contains the value.
+    Contains(String, JsonValue),
+    Exists(String),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    pub fn eq<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Eq(path.to_string(), value.into())
+    }
+
+    pub fn ne<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Ne(path.to_string(), value.into())
+    }
+
+    pub fn gt<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Gt(path.to_string(), value.into())
+    }
+
+    pub fn gte<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Gte(path.to_string(), value.into())
+    }
+
+    pub fn lt<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Lt(path.to_string(), value.into())
+    }
+
+    pub fn lte<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Lte(path.to_string(), value.into())
+    }
+
+    pub fn is_in<I, V>(path: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<JsonValue>,
+    {
+        Filter::In(path.to_string(), values.into_iter().map(Into::into).collect())
+    }
+
+    pub fn contains<V: Into<JsonValue>>(path: &str, value: V) -> Self {
+        Filter::Contains(path.to_string(), value.into())
+    }
+
+    pub fn exists(path: &str) -> Self {
+        Filter::Exists(path.to_string())
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        match self {
+            Filter::And(mut all) => {
+                all.push(other);
+                Filter::And(all)
+            }
+            f => Filter::And(vec![f, other]),
+        }
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        match self {
+            Filter::Or(mut any) => {
+                any.push(other);
+                Filter::Or(any)
+            }
+            f => Filter::Or(vec![f, other]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Filter::Not(Box::new(self))
+    }
+
+    /// Evaluate against a document. Non-object documents only match `Not(..)`.
+    pub fn matches(&self, doc: &JsonValue) -> bool {
+        let empty = Map::new();
+        let obj = doc.as_object().unwrap_or(&empty);
+        self.matches_obj(obj)
+    }
+
+    fn matches_obj(&self, obj: &Map<String, JsonValue>) -> bool {
+        match self {
+            Filter::Eq(p, v) => get_deep(obj, p).is_some_and(|f| compare(f, v) == Some(Ordering::Equal)),
+            Filter::Ne(p, v) => get_deep(obj, p).is_none_or(|f| compare(f, v) != Some(Ordering::Equal)),
+            Filter::Gt(p, v) => get_deep(obj, p).is_some_and(|f| compare(f, v) == Some(Ordering::Greater)),
+            Filter::Gte(p, v) => get_deep(obj, p)
+                .is_some_and(|f| matches!(compare(f, v), Some(Ordering::Greater | Ordering::Equal))),
+            Filter::Lt(p, v) => get_deep(obj, p).is_some_and(|f| compare(f, v) == Some(Ordering::Less)),
+            Filter::Lte(p, v) => get_deep(obj, p)
+                .is_some_and(|f| matches!(compare(f, v), Some(Ordering::Less | Ordering::Equal))),
+            Filter::In(p, vals) => get_deep(obj, p)
+                .is_some_and(|f| vals.iter().any(|v| compare(f, v) == Some(Ordering::Equal))),
+            Filter::Contains(p, v) => get_deep(obj, p)
+                .and_then(|f| f.as_array())
+                .is_some_and(|arr| arr.iter().any(|item| compare(item, v) == Some(Ordering::Equal))),
+            Filter::Exists(p) => get_deep(obj, p).is_some(),
+            Filter::And(all) => all.iter().all(|f| f.matches_obj(obj)),
+            Filter::Or(any) => any.iter().any(|f| f.matches_obj(obj)),
+            Filter::Not(f) => !f.matches_obj(obj),
+        }
+    }
+}
+
+/// Order two JSON values of the same kind; numbers compare across i64/f64.
+pub(crate) fn compare(a: &JsonValue, b: &JsonValue) -> Option<Ordering> {
+    match (a, b) {
+        (JsonValue::Number(x), JsonValue::Number(y)) => match (x.as_i64(), y.as_i64()) {
+            (Some(x), Some(y)) => Some(x.cmp(&y)),
+            _ => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        },
+        (JsonValue::String(x), JsonValue::String(y)) => Some(x.cmp(y)),
+        (JsonValue::Bool(x), JsonValue::Bool(y)) => Some(x.cmp(y)),
+        (JsonValue::Null, JsonValue::Null) => Some(Ordering::Equal),
+        (x, y) if x == y => Some(Ordering::Equal),
+        _ => None,
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+use serde_json::Value as JsonValue;
+use tikv_rust::{BoundRange, Key, Value};
+use crate::codec::decode_doc;
+use crate::config::DbConfig;
+use crate::errors::OsunbitDBError;
+use crate::filter::Filter;
+use crate::projection::FieldMask;
+use crate::utils::META_PREFIX;
+use crate::scan::{collection_range, max_lower, prefix_end, Direction, Reader, MAX_SCAN_LIMIT};
+
+/// Query over every collection whose last path segment is `name`, e.g. all
+/// `users:{uid}:inbox` collections for `CollectionGroup::new("inbox")`.
+///
+/// Without `within`, the whole keyspace is scanned. Only keys are scanned;
+/// values are fetched for documents in a matching collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionGroup {
+    pub name: String,
+    /// Only look under this collection path (e.g. "users").
+    pub parent: Option<String>,
+    pub filter: Option<Filter>,
+    pub mask: Option<FieldMask>,
+    pub limit: Option<usize>,
+    /// Full path of the last document of a previous page.
+    pub start_after: Option<String>,
+}
+
+impl CollectionGroup {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            parent: None,
+            filter: None,
+            mask: None,
+            limit: None,
+            start_after: None,
+        }
+    }
+
+    pub fn within(mut self, parent: &str) -> Self {
+        self.parent = Some(parent.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn mask(mut self, mask: FieldMask) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn start_after(mut self, path: &str) -> Self {
+        self.start_after = Some(path.to_string());
+        self
+    }
+}
+
+/// A document found by a collection-group query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupDoc {
+    /// Full key, `collection:id`.
+    pub path: String,
+    pub collection: String,
+    pub id: String,
+    pub doc: JsonValue,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupResult {
+    pub docs: Vec<GroupDoc>,
+    /// Paths whose stored value could not be decoded, with the decode error.
+    pub corrupt: Vec<(String, String)>,
+    /// Path to pass to `start_after` for the next page, `None` when done.
+    pub next: Option<String>,
+}
+
+/// Split `a:b:inbox:m1` into (`a:b:inbox`, `m1`) when the collection's last segment is `name`.
+fn split_path<'a>(path: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let (collection, id) = path.rsplit_once(':')?;
+    let last = collection.rsplit(':').next()?;
+    (last == name).then_some((collection, id))
+}
+
+pub(crate) async fn collection_group(
+    reader: &mut Reader<'_>,
+    config: &DbConfig,
+    query: &CollectionGroup,
+) -> Result<GroupResult, OsunbitDBError> {
+    if query.name.is_empty() || query.name.contains(':') {
+        return Err(OsunbitDBError::invalid_argument(format!(
+            "collection group name must be a single path segment, got {:?}", query.name
+        )));
+    }
+    config.limits.check_collection(&query.name)?;
+    if let Some(parent) = &query.parent {
+        config.limits.check_collection(parent)?;
+    }
+
+    let mut range = match &query.parent {
+        Some(parent) => collection_range(parent, Bound::Unbounded, Bound::Unbounded),
+        // Internal bookkeeping keys (blobs, chunks, counters) sort first and are not documents
+        None => BoundRange::new(Bound::Included(Key::from(prefix_end(META_PREFIX.as_bytes()).unwrap_or_default())), Bound::Unbounded),
+    };
+    if let Some(after) = &query.start_after {
+        range.from = max_lower(range.from, Bound::Excluded(Key::from(after.clone())));
+    }
+
+    let limit = query.limit.unwrap_or(usize::MAX);
+    let mut result = GroupResult::default();
+    if limit == 0 {
+        return Ok(result);
+    }
+
+    // Match on keys alone, then fetch only the values of matching documents
+    loop {
+        let page = reader.scan_page(range.clone(), MAX_SCAN_LIMIT, Direction::Ascending, true).await?;
+        let exhausted = page.len() < MAX_SCAN_LIMIT as usize;
+        if let Some((last, _)) = page.last() {
+            range.from = Bound::Excluded(last.clone());
+        }
+
+        let keys: Vec<Key> = page
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| split_path(&String::from_utf8_lossy(key.into()), &query.name).is_some())
+            .collect();
+        let mut values: HashMap<Key, Value> = reader
+            .batch_get(keys.clone())
+            .await?
+            .into_iter()
+            .filter_map(|(k, v)| Some((k, v?)))
+            .collect();
+
+        for key in keys {
+            let path = String::from_utf8_lossy((&key).into()).to_string();
+            let Some(bytes) = values.remove(&key) else { continue };
+            let Some((collection, id)) = split_path(&path, &query.name) else { continue };
+            let doc = match decode_doc(config, collection, id, &bytes) {
+                Ok(doc) => doc,
+                Err(e) => {
+                    result.corrupt.push((path, e.to_string()));
+                    continue;
+                }
+            };
+            if !query.filter.as_ref().is_none_or(|f| f.matches(&doc)) {
+                continue;
+            }
+
+            let doc = match &query.mask {
+                Some(mask) => mask.apply(&doc),
+                None => doc,
+            };
+            result.docs.push(GroupDoc {
+                collection: collection.to_string(),
+                id: id.to_string(),
+                path: path.clone(),
+                doc,
+            });
+            if result.docs.len() == limit {
+                result.next = Some(path);
+                return Ok(result);
+            }
+        }
+        if exhausted {
+            return Ok(result);
+        }
+    }
+}
+
+/// Direct child collection names under `parent` (`""` for the root, otherwise
+/// a document path), sorted.
+///
+/// A child `c` is reported when some key starts with `parent:c:`, so for
+/// `users:u1:inbox:m1` the root lists `users` and `users:u1` lists `inbox`.
+/// Each child costs one keys-only read: after finding `parent:c:...` the scan
+/// jumps straight past every key under it.
+pub(crate) async fn list_collections(reader: &mut Reader<'_>, parent: &str) -> Result<Vec<String>, OsunbitDBError> {
+    // Under a collection the next segment is a document id, not a collection
+    if !parent.is_empty() && parent.split(':').count() % 2 == 1 {
+        return Err(OsunbitDBError::invalid_argument(format!(
+            "list_collections expects \"\" or a document path like users:u1, got collection {:?}", parent
+        )));
+    }
+    let prefix = if parent.is_empty() { String::new() } else { format!("{}:", parent) };
+    let mut range = if prefix.is_empty() {
+        BoundRange::new(Bound::Included(Key::from(Vec::new())), Bound::Unbounded)
+    } else {
+        collection_range(parent, Bound::Unbounded, Bound::Unbounded)
+    };
+
+    let mut children = Vec::new();
+    loop {
+        let page = reader.scan_page(range.clone(), 1, Direction::Ascending, true).await?;
+        let Some((key, _)) = page.into_iter().next() else {
+            break;
+        };
+
+        let bytes: &[u8] = (&key).into();
+        if prefix.is_empty() && bytes.starts_with(META_PREFIX.as_bytes()) {
+            // Internal bookkeeping keys are not collections
+            match prefix_end(META_PREFIX.as_bytes()) {
+                Some(next) => range.from = Bound::Included(Key::from(next)),
+                None => break,
+            }
+            continue;
+        }
+        let rest = &bytes[prefix.len()..];
+        match rest.iter().position(|b| *b == b':') {
+            Some(i) => {
+                children.push(String::from_utf8_lossy(&rest[..i]).to_string());
+                // Skip every key under "parent:child:"
+                match prefix_end(&bytes[..prefix.len() + i + 1]) {
+                    Some(next) => range.from = Bound::Included(Key::from(next)),
+                    None => break,
+                }
+            }
+            // A plain document of `parent`, not a collection
+            None => range.from = Bound::Excluded(key),
+        }
+    }
+    Ok(children)
+}
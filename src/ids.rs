@@ -0,0 +1,61 @@
+use std::fmt;
+use std::sync::Arc;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// Strategy for `add_auto` document ids.
+#[derive(Clone, Default)]
+pub enum IdGenerator {
+    /// 20 random alphanumeric characters, like Firestore auto ids.
+    #[default]
+    Random,
+    /// 26-char ULID, lexicographically ordered by creation time.
+    Ulid,
+    /// Hyphenated UUIDv7, time-ordered.
+    UuidV7,
+    /// Per-collection counter incremented inside the transaction, zero-padded to
+    /// 20 digits so ids sort numerically.
+    Counter,
+    /// Any user supplied function.
+    Custom(Arc<dyn Fn() -> String + Send + Sync>),
+}
+
+impl IdGenerator {
+    pub fn custom<F: Fn() -> String + Send + Sync + 'static>(f: F) -> Self {
+        IdGenerator::Custom(Arc::new(f))
+    }
+
+    /// Generate an id without storage access; `None` for `Counter`.
+    pub fn generate(&self) -> Option<String> {
+        match self {
+            IdGenerator::Random => Some(
+                rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(20)
+                    .map(char::from)
+                    .collect(),
+            ),
+            IdGenerator::Ulid => Some(ulid::Ulid::new().to_string()),
+            IdGenerator::UuidV7 => Some(uuid::Uuid::now_v7().to_string()),
+            IdGenerator::Counter => None,
+            IdGenerator::Custom(f) => Some(f()),
+        }
+    }
+}
+
+impl fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdGenerator::Random => write!(f, "Random"),
+            IdGenerator::Ulid => write!(f, "Ulid"),
+            IdGenerator::UuidV7 => write!(f, "UuidV7"),
+            IdGenerator::Counter => write!(f, "Counter"),
+            IdGenerator::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Format a counter value as an id.
+pub(crate) fn counter_id(n: u64) -> String {
+    format!("{:020}", n)
+}
@@ -1,11 +1,46 @@
+pub mod aggregate;
+pub mod blob;
+pub mod bulk;
+mod chunk;
 pub mod client;
+pub mod codec;
+pub mod compression;
+pub mod config;
+pub mod cursor;
+pub mod encryption;
 pub mod transaction;
 pub mod errors;
+pub mod filter;
+pub mod group;
+pub mod ids;
+mod migration;
 pub mod ops;
+pub mod projection;
+pub mod scan;
+#[cfg(feature = "schema")]
+pub mod schema;
 pub mod utils;
+pub mod validation;
 
+pub use aggregate::{AggregateOp, AggregateResult, Aggregation};
+pub use blob::{Blob, BlobInfo, BlobReader, BlobWriter};
+pub use bulk::{BulkOptions, BulkSummary, ChunkProgress};
+pub use chunk::DEFAULT_CHUNK_SIZE;
 pub use client::OsunbitDB;
-pub use transaction::TransactionHandle;
-pub use errors::OsunbitDBError;
-pub use ops::{increment, remove, array_union, array_remove};
+pub use codec::Codec;
+pub use compression::{Compression, MAX_DECOMPRESSED_BYTES};
+pub use encryption::{Cipher, FieldEncryption, KeyProvider, StaticKeys};
+pub use config::CollectionConfig;
+pub use cursor::Cursor;
+pub use transaction::{BatchGetResult, TransactionHandle, WriteResult, CREATED_AT, UPDATED_AT};
+pub use errors::{ErrorContext, OsunbitDBError};
+pub use filter::Filter;
+pub use group::{CollectionGroup, GroupDoc, GroupResult};
+pub use ids::IdGenerator;
+pub use projection::FieldMask;
+#[cfg(feature = "schema")]
+pub use schema::Schema;
+pub use validation::{Limits, Violation};
+pub use scan::{CollectionStats, Consistency, Direction, ScanOptions, ScanResult, StreamOptions};
+pub use ops::{increment, remove, array_union, array_remove, server_timestamp, Update, FieldOp, IntoUpdate};
 pub use serde_json::{json, Value as Json};
@@ -0,0 +1,41 @@
+use std::fmt;
+use std::sync::Arc;
+use serde_json::Value as JsonValue;
+use crate::errors::OsunbitDBError;
+
+type Step = Arc<dyn Fn(JsonValue) -> Result<JsonValue, OsunbitDBError> + Send + Sync>;
+
+/// Ordered migration steps of a collection: step `i` turns a version `i + 1`
+/// document into version `i + 2`. Documents written before versioning was
+/// enabled count as version 1.
+#[derive(Clone, Default)]
+pub(crate) struct Migrations {
+    steps: Vec<Step>,
+}
+
+impl Migrations {
+    pub(crate) fn push(&mut self, step: Step) {
+        self.steps.push(step);
+    }
+
+    /// Version new writes are tagged with.
+    pub(crate) fn current(&self) -> u32 {
+        self.steps.len() as u32 + 1
+    }
+
+    /// Bring a `version` document up to `current`. Documents from a newer
+    /// version (written by a newer client) are returned as they are.
+    pub(crate) fn apply(&self, version: u32, mut doc: JsonValue) -> Result<JsonValue, OsunbitDBError> {
+        let from = version.max(1) as usize - 1;
+        for step in self.steps.iter().skip(from) {
+            doc = step(doc)?;
+        }
+        Ok(doc)
+    }
+}
+
+impl fmt::Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Migrations(v{})", self.current())
+    }
+}
@@ -1,5 +1,6 @@
 use serde_json::json;
 use serde_json::Value as Json;
+use crate::errors::OsunbitDBError;
 
 pub fn increment<T: Into<Json>>(amount: T) -> Json {
     json!({ "__op": "inc", "amount": amount.into() })
@@ -16,3 +17,194 @@ pub fn array_union(values: Json) -> Json {
 pub fn array_remove(values: Json) -> Json {
 json!({ "__op": "array_remove", "values": values })
 }
+
+/// Placeholder replaced by the transaction's timestamp (ms since epoch) when written.
+pub fn server_timestamp() -> Json {
+    json!({ "__op": "server_timestamp" })
+}
+
+pub(crate) fn is_server_timestamp(value: &Json) -> bool {
+    value.get("__op").and_then(|op| op.as_str()) == Some("server_timestamp")
+}
+
+/// Whether `value` is, or has inside its objects, a placeholder that a later
+/// `add`/`set` of the same document would resolve.
+fn has_server_timestamp(value: &Json) -> bool {
+    is_server_timestamp(value) || value.as_object().is_some_and(|obj| obj.values().any(has_server_timestamp))
+}
+
+/// A single field operation inside an `Update`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldOp {
+    /// Replace the field with a value, stored as-is even if it contains `__op`.
+    /// A `server_timestamp()` placeholder inside it is rejected with
+    /// `InvalidUpdate`; use `ServerTimestamp` for that path instead.
+    Set(Json),
+    /// Add a number (i64 stays i64 unless it overflows, anything else becomes
+    /// f64). Non-numeric amounts are rejected with `InvalidUpdate`.
+    Increment(Json),
+    /// Delete the field.
+    Remove,
+    /// Append values not already present in the array.
+    ArrayUnion(Vec<Json>),
+    /// Drop every occurrence of the values from the array.
+    ArrayRemove(Vec<Json>),
+    /// Set the field to the transaction's timestamp (ms since epoch).
+    ServerTimestamp,
+}
+
+/// Typed update builder, an alternative to the `json!` + `__op` helper form:
+/// `Update::new().inc("balance", 25).remove("role").array_union("tags", ["rust"])`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Update {
+    ops: Vec<(String, FieldOp)>,
+}
+
+impl Update {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set<V: Into<Json>>(self, path: &str, value: V) -> Self {
+        self.op(path, FieldOp::Set(value.into()))
+    }
+
+    pub fn inc<V: Into<Json>>(self, path: &str, amount: V) -> Self {
+        self.op(path, FieldOp::Increment(amount.into()))
+    }
+
+    pub fn remove(self, path: &str) -> Self {
+        self.op(path, FieldOp::Remove)
+    }
+
+    pub fn array_union<I, V>(self, path: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Json>,
+    {
+        self.op(path, FieldOp::ArrayUnion(values.into_iter().map(Into::into).collect()))
+    }
+
+    pub fn array_remove<I, V>(self, path: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Json>,
+    {
+        self.op(path, FieldOp::ArrayRemove(values.into_iter().map(Into::into).collect()))
+    }
+
+    pub fn server_timestamp(self, path: &str) -> Self {
+        self.op(path, FieldOp::ServerTimestamp)
+    }
+
+    /// Push a raw operation, fields are applied in insertion order.
+    pub fn op(mut self, path: &str, op: FieldOp) -> Self {
+        self.ops.push((path.to_string(), op));
+        self
+    }
+
+    pub fn ops(&self) -> &[(String, FieldOp)] {
+        &self.ops
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Parse the `json!` form: plain values are sets, `{"__op": ...}` objects are
+    /// helpers. `server_timestamp()` placeholders nested in a set value become
+    /// `ServerTimestamp` ops on their dotted path, applied after the set.
+    pub fn from_json(fields: &Json) -> Result<Self, OsunbitDBError> {
+        let map = fields.as_object().ok_or_else(|| {
+            OsunbitDBError::invalid_update("update fields must be an object".to_string())
+        })?;
+
+        let mut update = Update::new();
+        for (k, v) in map {
+            let op = match v.get("__op").and_then(|op| op.as_str()) {
+                Some("inc") => FieldOp::Increment(v["amount"].clone()),
+                Some("remove") => FieldOp::Remove,
+                Some("array_union") => FieldOp::ArrayUnion(v["values"].as_array().cloned().unwrap_or_default()),
+                Some("array_remove") => FieldOp::ArrayRemove(v["values"].as_array().cloned().unwrap_or_default()),
+                Some("server_timestamp") => FieldOp::ServerTimestamp,
+                _ => {
+                    let mut value = v.clone();
+                    let mut stamps = Vec::new();
+                    take_server_timestamps(&mut value, k, &mut stamps);
+                    update = update.op(k, FieldOp::Set(value));
+                    for path in stamps {
+                        update = update.op(&path, FieldOp::ServerTimestamp);
+                    }
+                    continue;
+                }
+            };
+            update = update.op(k, op);
+        }
+        update.checked()
+    }
+
+    /// Reject operations that cannot be applied, checked before any write.
+    pub(crate) fn checked(self) -> Result<Self, OsunbitDBError> {
+        for (path, op) in &self.ops {
+            match op {
+                FieldOp::Increment(amount) if !amount.is_number() => {
+                    return Err(OsunbitDBError::invalid_update(format!("increment of {} by non-numeric {}", path, amount)));
+                }
+                // Stored as-is it would turn into a timestamp on the next write of the document
+                FieldOp::Set(value) if has_server_timestamp(value) => {
+                    return Err(OsunbitDBError::invalid_update(format!(
+                        "set of {} contains server_timestamp(), use Update::server_timestamp", path
+                    )));
+                }
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Remove `server_timestamp()` placeholders from the objects inside `value`,
+/// collecting their dotted paths under `path`.
+fn take_server_timestamps(value: &mut Json, path: &str, out: &mut Vec<String>) {
+    if let Json::Object(obj) = value {
+        obj.retain(|k, v| {
+            let child = format!("{}.{}", path, k);
+            if is_server_timestamp(v) {
+                out.push(child);
+                false
+            } else {
+                take_server_timestamps(v, &child, out);
+                true
+            }
+        });
+    }
+}
+
+/// Anything `update` accepts: an `Update` builder or the `json!` form.
+pub trait IntoUpdate {
+    fn into_update(self) -> Result<Update, OsunbitDBError>;
+}
+
+impl IntoUpdate for Update {
+    fn into_update(self) -> Result<Update, OsunbitDBError> {
+        self.checked()
+    }
+}
+
+impl IntoUpdate for &Update {
+    fn into_update(self) -> Result<Update, OsunbitDBError> {
+        self.clone().checked()
+    }
+}
+
+impl IntoUpdate for &Json {
+    fn into_update(self) -> Result<Update, OsunbitDBError> {
+        Update::from_json(self)
+    }
+}
+
+impl IntoUpdate for Json {
+    fn into_update(self) -> Result<Update, OsunbitDBError> {
+        Update::from_json(&self)
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use serde_json::{Value as JsonValue, Map};
+
+/// Field mask applied client-side to trim documents before they are returned.
+///
+/// Paths use dot notation; `*` matches every key of an object or every element
+/// of an array: `["name", "profile.avatar", "items.*.sku"]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldMask {
+    root: Node,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Node {
+    /// A path ends here, keep the whole subtree.
+    leaf: bool,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn merge(&mut self, other: &Node) {
+        self.leaf |= other.leaf;
+        for (k, child) in &other.children {
+            self.children.entry(k.clone()).or_default().merge(child);
+        }
+    }
+}
+
+impl FieldMask {
+    pub fn new<I, S>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut root = Node::default();
+        for path in paths {
+            let path = path.as_ref().trim();
+            if path.is_empty() {
+                continue;
+            }
+            let mut node = &mut root;
+            for part in path.split('.') {
+                node = node.children.entry(part.to_string()).or_default();
+            }
+            node.leaf = true;
+        }
+        Self { root }
+    }
+
+    /// Comma separated paths, e.g. from a `?fields=name,profile.avatar` query string.
+    pub fn parse(fields: &str) -> Self {
+        Self::new(fields.split(','))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.children.is_empty()
+    }
+
+    /// Keep only the masked paths of `doc`. An empty mask keeps everything.
+    pub fn apply(&self, doc: &JsonValue) -> JsonValue {
+        if self.is_empty() {
+            return doc.clone();
+        }
+        project(doc, &self.root).unwrap_or_else(|| JsonValue::Object(Map::new()))
+    }
+}
+
+fn project(value: &JsonValue, node: &Node) -> Option<JsonValue> {
+    if node.leaf {
+        return Some(value.clone());
+    }
+
+    match value {
+        JsonValue::Object(obj) => {
+            let star = node.children.get("*");
+            let mut out = Map::new();
+            for (k, v) in obj {
+                let child = match (node.children.get(k), star) {
+                    (Some(exact), Some(star)) => {
+                        let mut merged = exact.clone();
+                        merged.merge(star);
+                        project(v, &merged)
+                    }
+                    (Some(exact), None) => project(v, exact),
+                    (None, Some(star)) => project(v, star),
+                    (None, None) => None,
+                };
+                if let Some(child) = child {
+                    out.insert(k.clone(), child);
+                }
+            }
+            if out.is_empty() { None } else { Some(JsonValue::Object(out)) }
+        }
+        JsonValue::Array(items) => {
+            let star = node.children.get("*")?;
+            Some(JsonValue::Array(items.iter().filter_map(|item| project(item, star)).collect()))
+        }
+        _ => None,
+    }
+}
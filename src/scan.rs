@@ -0,0 +1,730 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde_json::Value as JsonValue;
+use tikv_rust::{BoundRange, CheckLevel, Key, KvPair, Snapshot, Timestamp, Transaction, TransactionClient, TransactionOptions, Value};
+use std::sync::Arc;
+use crate::chunk::Manifest;
+use crate::codec::decode_doc;
+use crate::config::DbConfig;
+use crate::cursor::Cursor;
+use crate::errors::OsunbitDBError;
+use crate::filter::Filter;
+use crate::projection::FieldMask;
+
+/// Key order of a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Largest `limit` a single scan accepts.
+pub const MAX_SCAN_LIMIT: u32 = 10240;
+
+impl Direction {
+    /// Parse the legacy `order` string: "a"/"asc" or "d"/"desc", case-insensitive.
+    pub fn parse(order: &str) -> Result<Self, OsunbitDBError> {
+        match order.to_ascii_lowercase().as_str() {
+            "a" | "asc" | "ascending" => Ok(Direction::Ascending),
+            "d" | "desc" | "descending" => Ok(Direction::Descending),
+            _ => Err(OsunbitDBError::invalid_argument(format!(
+                "scan order must be \"a\" or \"d\", got {:?}", order
+            ))),
+        }
+    }
+}
+
+/// Options for `scan_with`. Bounds are document ids and apply in id order
+/// whatever the direction; `prefix` further narrows the range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOptions {
+    pub start: Option<String>,
+    pub start_inclusive: bool,
+    pub end: Option<String>,
+    pub end_inclusive: bool,
+    pub prefix: Option<String>,
+    pub direction: Direction,
+    /// Skip reading values, documents come back as `Null`.
+    pub keys_only: bool,
+    pub limit: u32,
+    /// Only return matching documents; scanning continues until `limit` matches.
+    pub filter: Option<Filter>,
+    /// Token from a previous `ScanResult::cursor`, continues after its last key.
+    pub cursor: Option<String>,
+    /// With `Snapshot`, `OsunbitDB::scan_with` reads a cursor's pages at the
+    /// cursor's timestamp (only within TiKV's GC window).
+    pub consistency: Consistency,
+    /// Trim returned documents; the filter still sees the full document.
+    pub mask: Option<FieldMask>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            start: None,
+            start_inclusive: true,
+            end: None,
+            end_inclusive: false,
+            prefix: None,
+            direction: Direction::Ascending,
+            keys_only: false,
+            limit: 100,
+            filter: None,
+            cursor: None,
+            consistency: Consistency::Fresh,
+            mask: None,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_at(mut self, id: &str) -> Self {
+        self.start = Some(id.to_string());
+        self.start_inclusive = true;
+        self
+    }
+
+    pub fn start_after(mut self, id: &str) -> Self {
+        self.start = Some(id.to_string());
+        self.start_inclusive = false;
+        self
+    }
+
+    pub fn end_at(mut self, id: &str) -> Self {
+        self.end = Some(id.to_string());
+        self.end_inclusive = true;
+        self
+    }
+
+    pub fn end_before(mut self, id: &str) -> Self {
+        self.end = Some(id.to_string());
+        self.end_inclusive = false;
+        self
+    }
+
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn keys_only(mut self, keys_only: bool) -> Self {
+        self.keys_only = keys_only;
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn cursor(mut self, token: &str) -> Self {
+        self.cursor = Some(token.to_string());
+        self
+    }
+
+    pub fn consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    pub fn mask(mut self, mask: FieldMask) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), OsunbitDBError> {
+        if self.limit == 0 || self.limit > MAX_SCAN_LIMIT {
+            return Err(OsunbitDBError::invalid_argument(format!(
+                "scan limit must be between 1 and {}, got {}", MAX_SCAN_LIMIT, self.limit
+            )));
+        }
+        if let (Some(start), Some(end)) = (&self.start, &self.end) {
+            let empty = start > end || (start == end && !(self.start_inclusive && self.end_inclusive));
+            if empty {
+                return Err(OsunbitDBError::invalid_argument(format!(
+                    "scan start {:?} is not before end {:?}", start, end
+                )));
+            }
+        }
+        if self.keys_only && self.filter.is_some() {
+            return Err(OsunbitDBError::invalid_argument(
+                "a filter needs document values, it cannot be combined with keys_only".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Key range for `collection`, intersected with the prefix range.
+    pub(crate) fn range(&self, collection: &str) -> BoundRange {
+        fn bound(id: &Option<String>, inclusive: bool) -> Bound<&str> {
+            match id.as_deref() {
+                Some(id) if inclusive => Bound::Included(id),
+                Some(id) => Bound::Excluded(id),
+                None => Bound::Unbounded,
+            }
+        }
+        let mut range = collection_range(
+            collection,
+            bound(&self.start, self.start_inclusive),
+            bound(&self.end, self.end_inclusive),
+        );
+
+        if let Some(prefix) = &self.prefix {
+            let low = format!("{}:{}", collection, prefix).into_bytes();
+            let high = prefix_end(&low).map(Key::from);
+            range.from = max_lower(range.from, Bound::Included(Key::from(low)));
+            if let Some(high) = high {
+                range.to = min_upper(range.to, Bound::Excluded(high));
+            }
+        }
+        range
+    }
+}
+
+/// Smallest key greater than every key starting with `prefix`.
+pub(crate) fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xFF {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+pub(crate) fn max_lower(a: Bound<Key>, b: Bound<Key>) -> Bound<Key> {
+    match (&a, &b) {
+        (Bound::Unbounded, _) => b,
+        (_, Bound::Unbounded) => a,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y)) => {
+            if x > y || (x == y && matches!(a, Bound::Excluded(_))) { a } else { b }
+        }
+    }
+}
+
+pub(crate) fn min_upper(a: Bound<Key>, b: Bound<Key>) -> Bound<Key> {
+    match (&a, &b) {
+        (Bound::Unbounded, _) => b,
+        (_, Bound::Unbounded) => a,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y)) => {
+            if x < y || (x == y && matches!(a, Bound::Excluded(_))) { a } else { b }
+        }
+    }
+}
+
+/// One page of `scan_with`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanResult {
+    /// `(id, document)` in scan order, documents are `Null` in keys-only mode.
+    /// Subcollection documents are left out.
+    pub items: Vec<(String, JsonValue)>,
+    /// Opaque token for the next page, `None` once the range is exhausted.
+    pub cursor: Option<String>,
+}
+
+impl ScanResult {
+    pub fn ids(&self) -> Vec<&str> {
+        self.items.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    /// Id of the last item, to continue with `start_after` / `end_before`.
+    pub fn last_id(&self) -> Option<&str> {
+        self.items.last().map(|(id, _)| id.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Transactions and snapshots expose the same read API; scans run on either.
+pub(crate) enum Reader<'a> {
+    Tx(&'a mut Transaction),
+    Snapshot(&'a mut Snapshot),
+}
+
+impl Reader<'_> {
+    pub(crate) async fn get(&mut self, key: Key) -> Result<Option<Value>, OsunbitDBError> {
+        Ok(match self {
+            Reader::Tx(tx) => tx.get(key).await?,
+            Reader::Snapshot(s) => s.get(key).await?,
+        })
+    }
+
+    pub(crate) async fn key_exists(&mut self, key: Key) -> Result<bool, OsunbitDBError> {
+        Ok(match self {
+            Reader::Tx(tx) => tx.key_exists(key).await?,
+            Reader::Snapshot(s) => s.key_exists(key).await?,
+        })
+    }
+
+    /// Values of `keys`, chunked ones reassembled, in no particular order.
+    pub(crate) async fn batch_get(&mut self, keys: Vec<Key>) -> Result<Vec<(Key, Option<Value>)>, OsunbitDBError> {
+        let kvs: Vec<KvPair> = match self {
+            Reader::Tx(tx) => tx.batch_get(keys).await?.collect(),
+            Reader::Snapshot(s) => s.batch_get(keys).await?.collect(),
+        };
+        let mut page: Vec<(Key, Option<Value>)> = kvs
+            .into_iter()
+            .map(|kv| {
+                let (k, v): (Key, Value) = kv.into();
+                (k, Some(v))
+            })
+            .collect();
+        self.reassemble(&mut page).await?;
+        Ok(page)
+    }
+
+    /// Visit every key (and value unless `keys_only`) in `range`, `page_size`
+    /// keys per request. Stops early when `visit` returns `false`.
+    pub(crate) async fn walk<F>(
+        &mut self,
+        mut range: BoundRange,
+        direction: Direction,
+        keys_only: bool,
+        page_size: u32,
+        mut visit: F,
+    ) -> Result<(), OsunbitDBError>
+    where
+        F: FnMut(Key, Option<Value>) -> Result<bool, OsunbitDBError>,
+    {
+        let page_size = page_size.clamp(1, MAX_SCAN_LIMIT);
+        loop {
+            let page = self.scan_page(range.clone(), page_size, direction, keys_only).await?;
+            let exhausted = page.len() < page_size as usize;
+
+            for (key, value) in page {
+                match direction {
+                    Direction::Ascending => range.from = Bound::Excluded(key.clone()),
+                    Direction::Descending => range.to = Bound::Excluded(key.clone()),
+                }
+                if !visit(key, value)? {
+                    return Ok(());
+                }
+            }
+            if exhausted {
+                return Ok(());
+            }
+        }
+    }
+
+    pub(crate) async fn scan_page(
+        &mut self,
+        range: BoundRange,
+        limit: u32,
+        direction: Direction,
+        keys_only: bool,
+    ) -> Result<Vec<(Key, Option<Value>)>, OsunbitDBError> {
+        let reverse = direction == Direction::Descending;
+        if keys_only {
+            let keys: Vec<Key> = match (self, reverse) {
+                (Reader::Tx(tx), false) => tx.scan_keys(range, limit).await?.collect(),
+                (Reader::Tx(tx), true) => tx.scan_keys_reverse(range, limit).await?.collect(),
+                (Reader::Snapshot(s), false) => s.scan_keys(range, limit).await?.collect(),
+                (Reader::Snapshot(s), true) => s.scan_keys_reverse(range, limit).await?.collect(),
+            };
+            Ok(keys.into_iter().map(|k| (k, None)).collect())
+        } else {
+            let kvs: Vec<KvPair> = match (&mut *self, reverse) {
+                (Reader::Tx(tx), false) => tx.scan(range, limit).await?.collect(),
+                (Reader::Tx(tx), true) => tx.scan_reverse(range, limit).await?.collect(),
+                (Reader::Snapshot(s), false) => s.scan(range, limit).await?.collect(),
+                (Reader::Snapshot(s), true) => s.scan_reverse(range, limit).await?.collect(),
+            };
+            let mut page: Vec<(Key, Option<Value>)> = kvs.into_iter().map(|kv| {
+                let (k, v): (Key, Value) = kv.into();
+                (k, Some(v))
+            }).collect();
+            self.reassemble(&mut page).await?;
+            Ok(page)
+        }
+    }
+
+    /// Replace chunk manifests in `page` with the values they describe, all
+    /// chunks fetched in one `batch_get`. A manifest whose chunks are missing
+    /// or damaged is left in place and fails to decode.
+    pub(crate) async fn reassemble(&mut self, page: &mut [(Key, Option<Value>)]) -> Result<(), OsunbitDBError> {
+        let manifests: Vec<(usize, Manifest)> = page
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, v))| Some((i, Manifest::parse(v.as_deref()?)?)))
+            .collect();
+        if manifests.is_empty() {
+            return Ok(());
+        }
+
+        let keys: Vec<Key> = manifests.iter().flat_map(|(i, m)| m.keys(&page[*i].0)).collect();
+        let kvs: Vec<KvPair> = match self {
+            Reader::Tx(tx) => tx.batch_get(keys).await?.collect(),
+            Reader::Snapshot(s) => s.batch_get(keys).await?.collect(),
+        };
+        let mut chunks: HashMap<Key, Value> = kvs.into_iter().map(|kv| kv.into()).collect();
+
+        for (i, manifest) in manifests {
+            let parts = manifest.keys(&page[i].0).iter().map(|k| chunks.remove(k)).collect();
+            if let Ok(value) = manifest.assemble(parts) {
+                page[i].1 = Some(value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run one `scan_with` page on `reader`, which reads at timestamp `ts`.
+pub(crate) async fn scan_with(
+    mut reader: Reader<'_>,
+    config: &DbConfig,
+    collection: &str,
+    opts: &ScanOptions,
+    ts: u64,
+) -> Result<ScanResult, OsunbitDBError> {
+    opts.validate()?;
+    config.limits.check_collection(collection)?;
+    let cursor_key = config.cursor_key.as_deref();
+    let mut range = opts.range(collection);
+
+    if let Some(token) = &opts.cursor {
+        let cursor = Cursor::decode(token, cursor_key)?;
+        cursor.check(collection, opts.direction, opts.filter.as_ref())?;
+        let after = Bound::Excluded(Key::from(format!("{}:{}", collection, cursor.last_id)));
+        match opts.direction {
+            Direction::Ascending => range.from = max_lower(range.from, after),
+            Direction::Descending => range.to = min_upper(range.to, after),
+        }
+    }
+
+    let limit = opts.limit as usize;
+    let mut items = Vec::new();
+    let mut last_id = None;
+
+    loop {
+        let page = reader.scan_page(range.clone(), opts.limit, opts.direction, opts.keys_only).await?;
+        let exhausted = page.len() < limit;
+
+        for (key, value) in page {
+            let id = doc_id(collection, &key);
+            match opts.direction {
+                Direction::Ascending => range.from = Bound::Excluded(key),
+                Direction::Descending => range.to = Bound::Excluded(key),
+            }
+            // Subcollection documents are not part of this collection
+            if id.contains(':') {
+                continue;
+            }
+
+            let doc = match value {
+                Some(bytes) => decode_doc(config, collection, &id, &bytes).unwrap_or(JsonValue::Null),
+                None => JsonValue::Null,
+            };
+            if opts.filter.as_ref().is_none_or(|f| f.matches(&doc)) {
+                let doc = match &opts.mask {
+                    Some(mask) if !opts.keys_only => mask.apply(&doc),
+                    _ => doc,
+                };
+                items.push((id.clone(), doc));
+            }
+            last_id = Some(id);
+            if items.len() == limit {
+                break;
+            }
+        }
+
+        if items.len() == limit || exhausted {
+            break;
+        }
+    }
+
+    let cursor = match &last_id {
+        Some(last) if items.len() == limit => {
+            Some(Cursor::new(collection, opts.direction, last, opts.filter.as_ref(), ts).encode(cursor_key)?)
+        }
+        _ => None,
+    };
+
+    Ok(ScanResult { items, cursor })
+}
+
+/// Summary of a collection from `stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectionStats {
+    pub count: u64,
+    /// Stored value bytes, keys excluded.
+    pub total_bytes: u64,
+    pub min_id: Option<String>,
+    pub max_id: Option<String>,
+    pub largest_id: Option<String>,
+    pub largest_bytes: u64,
+}
+
+pub(crate) async fn exists(reader: &mut Reader<'_>, collection: &str, id: &str) -> Result<bool, OsunbitDBError> {
+    reader.key_exists(Key::from(format!("{}:{}", collection, id))).await
+}
+
+/// Count ids in the range of `opts`. Keys-only unless `opts.filter` needs values;
+/// `limit`, `cursor` and `mask` are ignored.
+pub(crate) async fn count(
+    reader: &mut Reader<'_>,
+    config: &DbConfig,
+    collection: &str,
+    opts: &ScanOptions,
+) -> Result<u64, OsunbitDBError> {
+    let mut check = opts.clone();
+    check.limit = 1;
+    check.validate()?;
+    config.limits.check_collection(collection)?;
+
+    let mut n = 0;
+    reader
+        .walk(opts.range(collection), Direction::Ascending, opts.filter.is_none(), MAX_SCAN_LIMIT, |key, value| {
+            // Subcollection documents are not part of this collection
+            let id = doc_id(collection, &key);
+            if id.contains(':') {
+                return Ok(true);
+            }
+            let matched = match (&opts.filter, value) {
+                (Some(filter), Some(bytes)) => {
+                    let doc = decode_doc(config, collection, &id, &bytes).unwrap_or(JsonValue::Null);
+                    filter.matches(&doc)
+                }
+                _ => true,
+            };
+            if matched {
+                n += 1;
+            }
+            Ok(true)
+        })
+        .await?;
+    Ok(n)
+}
+
+pub(crate) async fn stats(reader: &mut Reader<'_>, collection: &str) -> Result<CollectionStats, OsunbitDBError> {
+    let mut stats = CollectionStats::default();
+    let range = collection_range(collection, Bound::Unbounded, Bound::Unbounded);
+
+    reader
+        .walk(range, Direction::Ascending, false, MAX_SCAN_LIMIT, |key, value| {
+            let id = doc_id(collection, &key);
+            if id.contains(':') {
+                return Ok(true);
+            }
+            let bytes = value.map_or(0, |v| v.len() as u64);
+
+            stats.count += 1;
+            stats.total_bytes += bytes;
+            if stats.min_id.is_none() {
+                stats.min_id = Some(id.clone());
+            }
+            if stats.largest_id.is_none() || bytes > stats.largest_bytes {
+                stats.largest_id = Some(id.clone());
+                stats.largest_bytes = bytes;
+            }
+            stats.max_id = Some(id);
+            Ok(true)
+        })
+        .await?;
+    Ok(stats)
+}
+
+/// Which snapshot each page of a stream reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Consistency {
+    /// Every page reads at a new timestamp and sees writes committed meanwhile.
+    #[default]
+    Fresh,
+    /// All pages read at the timestamp taken when the stream started.
+    Snapshot,
+}
+
+/// Options for `OsunbitDB::stream`. Bounds are document ids: `start` inclusive,
+/// `end` exclusive, whatever the direction.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    pub page_size: u32,
+    pub direction: Direction,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub consistency: Consistency,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            page_size: 500,
+            direction: Direction::Ascending,
+            start: None,
+            end: None,
+            consistency: Consistency::Fresh,
+        }
+    }
+}
+
+impl StreamOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn start(mut self, id: &str) -> Self {
+        self.start = Some(id.to_string());
+        self
+    }
+
+    pub fn end(mut self, id: &str) -> Self {
+        self.end = Some(id.to_string());
+        self
+    }
+
+    pub fn consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+}
+
+/// Key range covering ids of `collection` between the given id bounds.
+pub(crate) fn collection_range(collection: &str, start: Bound<&str>, end: Bound<&str>) -> BoundRange {
+    let key = |id: &str| Key::from(format!("{}:{}", collection, id));
+    let from = match start {
+        Bound::Included(id) => Bound::Included(key(id)),
+        Bound::Excluded(id) => Bound::Excluded(key(id)),
+        Bound::Unbounded => Bound::Included(Key::from(format!("{}:", collection))),
+    };
+    let to = match end {
+        Bound::Included(id) => Bound::Included(key(id)),
+        Bound::Excluded(id) => Bound::Excluded(key(id)),
+        // ';' is the byte right after ':', so this bounds every "collection:..." key
+        Bound::Unbounded => Bound::Excluded(Key::from(format!("{};", collection))),
+    };
+    BoundRange::new(from, to)
+}
+
+/// Strip the `collection:` prefix from a raw key.
+pub(crate) fn doc_id(collection: &str, key: &Key) -> String {
+    let k = String::from_utf8_lossy(key.into()).to_string();
+    k.strip_prefix(&format!("{}:", collection)).unwrap_or(&k).to_string()
+}
+
+/// Read-only snapshot that can be dropped without commit/rollback.
+pub(crate) fn read_snapshot(client: &TransactionClient, ts: Timestamp) -> Snapshot {
+    let options = TransactionOptions::new_optimistic().drop_check(CheckLevel::None);
+    client.snapshot(ts, options)
+}
+
+struct StreamState {
+    client: TransactionClient,
+    config: Arc<DbConfig>,
+    collection: String,
+    opts: StreamOptions,
+    from: Bound<Key>,
+    to: Bound<Key>,
+    snapshot_ts: Option<Timestamp>,
+    done: bool,
+}
+
+pub(crate) fn stream(
+    client: TransactionClient,
+    config: Arc<DbConfig>,
+    collection: &str,
+    opts: StreamOptions,
+) -> BoxStream<'static, Result<(String, JsonValue), OsunbitDBError>> {
+    if let Err(e) = config.limits.check_collection(collection) {
+        return stream::once(async { Err(e) }).boxed();
+    }
+    let range = collection_range(
+        collection,
+        opts.start.as_deref().map_or(Bound::Unbounded, Bound::Included),
+        opts.end.as_deref().map_or(Bound::Unbounded, Bound::Excluded),
+    );
+    let (from, to) = (range.from.clone(), range.to.clone());
+
+    let state = StreamState {
+        client,
+        config,
+        collection: collection.to_string(),
+        opts,
+        from,
+        to,
+        snapshot_ts: None,
+        done: false,
+    };
+
+    stream::try_unfold(state, |mut state| async move {
+        if state.done {
+            return Ok(None);
+        }
+
+        let ts = match (state.opts.consistency, &state.snapshot_ts) {
+            (Consistency::Snapshot, Some(ts)) => ts.clone(),
+            (Consistency::Snapshot, None) => {
+                let ts = state.client.current_timestamp().await?;
+                state.snapshot_ts = Some(ts.clone());
+                ts
+            }
+            (Consistency::Fresh, _) => state.client.current_timestamp().await?,
+        };
+        let mut snapshot = read_snapshot(&state.client, ts);
+
+        let limit = state.opts.page_size.clamp(1, MAX_SCAN_LIMIT);
+        let range = BoundRange::new(state.from.clone(), state.to.clone());
+        let kvs = Reader::Snapshot(&mut snapshot).scan_page(range, limit, state.opts.direction, false).await?;
+
+        if kvs.len() < limit as usize {
+            state.done = true;
+        }
+        if let Some((last, _)) = kvs.last() {
+            match state.opts.direction {
+                Direction::Ascending => state.from = Bound::Excluded(last.clone()),
+                Direction::Descending => state.to = Bound::Excluded(last.clone()),
+            }
+        }
+
+        let page: Vec<Result<(String, JsonValue), OsunbitDBError>> = kvs
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let id = doc_id(&state.collection, &key);
+                // Subcollection documents are not part of this collection
+                if id.contains(':') {
+                    return None;
+                }
+                let doc = match value {
+                    Some(bytes) => decode_doc(&state.config, &state.collection, &id, &bytes).unwrap_or(JsonValue::Null),
+                    None => JsonValue::Null,
+                };
+                Some(Ok((id, doc)))
+            })
+            .collect();
+
+        Ok::<_, OsunbitDBError>(Some((stream::iter(page), state)))
+    })
+    .try_flatten()
+    .boxed()
+}
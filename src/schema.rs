@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use regex::Regex;
+use serde_json::{Map, Value as JsonValue};
+use crate::errors::OsunbitDBError;
+use crate::filter::compare;
+pub use crate::validation::Violation;
+
+/// Keywords of draft 2020-12 that this subset does not evaluate. Schemas using
+/// them are rejected instead of being silently half-enforced.
+const UNSUPPORTED: &[&str] = &[
+    "$ref", "$dynamicRef", "$defs", "definitions", "if", "then", "else",
+    "dependentRequired", "dependentSchemas", "patternProperties", "propertyNames",
+    "unevaluatedProperties", "unevaluatedItems", "contains", "minContains", "maxContains",
+];
+
+/// JSON Schema (draft 2020-12 subset) checked on every write to a collection,
+/// registered with `CollectionConfig::schema`.
+///
+/// Supported: `type`, `enum`, `const`, `properties`, `required`,
+/// `additionalProperties`, `minProperties`/`maxProperties`, `items`,
+/// `prefixItems`, `minItems`/`maxItems`, `uniqueItems`, `minLength`/`maxLength`,
+/// `pattern`, `minimum`/`maximum`, `exclusiveMinimum`/`exclusiveMaximum`,
+/// `multipleOf`, `allOf`/`anyOf`/`oneOf`/`not` and boolean schemas.
+/// Annotations (`title`, `format`, ...) are ignored.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: JsonValue,
+    patterns: HashMap<String, Regex>,
+}
+
+impl Schema {
+    pub fn new(schema: JsonValue) -> Result<Self, OsunbitDBError> {
+        let mut patterns = HashMap::new();
+        check(&schema, "#", &mut patterns)?;
+        Ok(Self { root: schema, patterns })
+    }
+
+    /// Every violation of `doc`, empty when it is valid.
+    pub fn validate(&self, doc: &JsonValue) -> Vec<Violation> {
+        let mut out = Vec::new();
+        self.eval(&self.root, doc, &mut String::new(), &mut out);
+        out
+    }
+
+    pub fn is_valid(&self, doc: &JsonValue) -> bool {
+        self.validate(doc).is_empty()
+    }
+
+    fn eval(&self, schema: &JsonValue, value: &JsonValue, path: &mut String, out: &mut Vec<Violation>) {
+        let s = match schema {
+            JsonValue::Bool(true) => return,
+            JsonValue::Bool(false) => return push(out, path, "not allowed".to_string()),
+            JsonValue::Object(s) => s,
+            _ => return,
+        };
+
+        if let Some(t) = s.get("type") {
+            let types: Vec<&str> = match t {
+                JsonValue::String(t) => vec![t.as_str()],
+                JsonValue::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            if !types.iter().any(|t| has_type(value, t)) {
+                push(out, path, format!("expected {}, got {}", types.join(" or "), type_name(value)));
+                // Other keywords would only repeat the type error
+                return;
+            }
+        }
+        if let Some(allowed) = s.get("enum").and_then(|e| e.as_array()) {
+            if !allowed.iter().any(|a| equal(a, value)) {
+                push(out, path, format!("must be one of {}", JsonValue::Array(allowed.clone())));
+            }
+        }
+        if let Some(c) = s.get("const") {
+            if !equal(c, value) {
+                push(out, path, format!("must be {}", c));
+            }
+        }
+
+        match value {
+            JsonValue::Number(_) => self.eval_number(s, value, path, out),
+            JsonValue::String(text) => self.eval_string(s, text, path, out),
+            JsonValue::Array(items) => self.eval_array(s, items, path, out),
+            JsonValue::Object(obj) => self.eval_object(s, obj, path, out),
+            _ => {}
+        }
+
+        if let Some(all) = s.get("allOf").and_then(|a| a.as_array()) {
+            for sub in all {
+                self.eval(sub, value, path, out);
+            }
+        }
+        if let Some(any) = s.get("anyOf").and_then(|a| a.as_array()) {
+            if !any.iter().any(|sub| self.passes(sub, value)) {
+                push(out, path, "must match at least one schema in anyOf".to_string());
+            }
+        }
+        if let Some(one) = s.get("oneOf").and_then(|a| a.as_array()) {
+            let n = one.iter().filter(|sub| self.passes(sub, value)).count();
+            if n != 1 {
+                push(out, path, format!("must match exactly one schema in oneOf, matched {}", n));
+            }
+        }
+        if let Some(not) = s.get("not") {
+            if self.passes(not, value) {
+                push(out, path, "must not match the schema in not".to_string());
+            }
+        }
+    }
+
+    fn passes(&self, schema: &JsonValue, value: &JsonValue) -> bool {
+        let mut out = Vec::new();
+        self.eval(schema, value, &mut String::new(), &mut out);
+        out.is_empty()
+    }
+
+    fn eval_number(&self, s: &Map<String, JsonValue>, value: &JsonValue, path: &str, out: &mut Vec<Violation>) {
+        use std::cmp::Ordering::*;
+        let bounds = [
+            ("minimum", [Greater, Equal].as_slice(), ">="),
+            ("maximum", [Less, Equal].as_slice(), "<="),
+            ("exclusiveMinimum", [Greater].as_slice(), ">"),
+            ("exclusiveMaximum", [Less].as_slice(), "<"),
+        ];
+        for (keyword, ok, op) in bounds {
+            if let Some(limit) = s.get(keyword) {
+                if !compare(value, limit).is_some_and(|o| ok.contains(&o)) {
+                    push(out, path, format!("must be {} {}", op, limit));
+                }
+            }
+        }
+        if let (Some(m), Some(v)) = (s.get("multipleOf").and_then(|m| m.as_f64()), value.as_f64()) {
+            let q = v / m;
+            if (q - q.round()).abs() > 1e-9 {
+                push(out, path, format!("must be a multiple of {}", m));
+            }
+        }
+    }
+
+    fn eval_string(&self, s: &Map<String, JsonValue>, text: &str, path: &str, out: &mut Vec<Violation>) {
+        let len = text.chars().count() as u64;
+        if let Some(min) = s.get("minLength").and_then(|m| m.as_u64()) {
+            if len < min {
+                push(out, path, format!("must be at least {} characters", min));
+            }
+        }
+        if let Some(max) = s.get("maxLength").and_then(|m| m.as_u64()) {
+            if len > max {
+                push(out, path, format!("must be at most {} characters", max));
+            }
+        }
+        if let Some(pattern) = s.get("pattern").and_then(|p| p.as_str()) {
+            if !self.patterns.get(pattern).is_some_and(|re| re.is_match(text)) {
+                push(out, path, format!("must match pattern {}", pattern));
+            }
+        }
+    }
+
+    fn eval_array(&self, s: &Map<String, JsonValue>, items: &[JsonValue], path: &mut String, out: &mut Vec<Violation>) {
+        let len = items.len() as u64;
+        if let Some(min) = s.get("minItems").and_then(|m| m.as_u64()) {
+            if len < min {
+                push(out, path, format!("must have at least {} items", min));
+            }
+        }
+        if let Some(max) = s.get("maxItems").and_then(|m| m.as_u64()) {
+            if len > max {
+                push(out, path, format!("must have at most {} items", max));
+            }
+        }
+        if s.get("uniqueItems") == Some(&JsonValue::Bool(true)) {
+            let dup = items.iter().enumerate().any(|(i, a)| items[..i].iter().any(|b| equal(a, b)));
+            if dup {
+                push(out, path, "items must be unique".to_string());
+            }
+        }
+
+        let prefix = s.get("prefixItems").and_then(|p| p.as_array()).map_or(&[][..], |p| p.as_slice());
+        for (i, item) in items.iter().enumerate() {
+            let sub = match prefix.get(i) {
+                Some(sub) => sub,
+                None => match s.get("items") {
+                    Some(sub) => sub,
+                    None => continue,
+                },
+            };
+            let len = path.len();
+            path.push_str(&format!("/{}", i));
+            self.eval(sub, item, path, out);
+            path.truncate(len);
+        }
+    }
+
+    fn eval_object(&self, s: &Map<String, JsonValue>, obj: &Map<String, JsonValue>, path: &mut String, out: &mut Vec<Violation>) {
+        if let Some(required) = s.get("required").and_then(|r| r.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !obj.contains_key(key) {
+                    push(out, &format!("{}/{}", path, escape(key)), "is required".to_string());
+                }
+            }
+        }
+        let len = obj.len() as u64;
+        if let Some(min) = s.get("minProperties").and_then(|m| m.as_u64()) {
+            if len < min {
+                push(out, path, format!("must have at least {} properties", min));
+            }
+        }
+        if let Some(max) = s.get("maxProperties").and_then(|m| m.as_u64()) {
+            if len > max {
+                push(out, path, format!("must have at most {} properties", max));
+            }
+        }
+
+        let properties = s.get("properties").and_then(|p| p.as_object());
+        for (key, value) in obj {
+            let sub = match properties.and_then(|p| p.get(key)) {
+                Some(sub) => sub,
+                None => match s.get("additionalProperties") {
+                    Some(sub) => sub,
+                    None => continue,
+                },
+            };
+            let len = path.len();
+            path.push('/');
+            path.push_str(&escape(key));
+            self.eval(sub, value, path, out);
+            path.truncate(len);
+        }
+    }
+}
+
+fn push(out: &mut Vec<Violation>, path: &str, message: String) {
+    out.push(Violation { id: String::new(), path: path.to_string(), message });
+}
+
+/// JSON pointer escaping of one segment.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Equality where `1` and `1.0` are the same number.
+fn equal(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(_), JsonValue::Number(_)) => compare(a, b) == Some(std::cmp::Ordering::Equal),
+        (JsonValue::Array(x), JsonValue::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(a, b)| equal(a, b)),
+        (JsonValue::Object(x), JsonValue::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+fn has_type(value: &JsonValue, t: &str) -> bool {
+    match t {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => false,
+    }
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_f64() => "number",
+        JsonValue::Number(_) => "integer",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// Reject malformed or unsupported schemas up front and compile patterns.
+fn check(schema: &JsonValue, at: &str, patterns: &mut HashMap<String, Regex>) -> Result<(), OsunbitDBError> {
+    let invalid = |msg: String| OsunbitDBError::invalid_argument(format!("schema {}: {}", at, msg));
+    let s = match schema {
+        JsonValue::Bool(_) => return Ok(()),
+        JsonValue::Object(s) => s,
+        _ => return Err(invalid("must be an object or boolean".to_string())),
+    };
+
+    if let Some(k) = UNSUPPORTED.iter().find(|k| s.contains_key(**k)) {
+        return Err(invalid(format!("keyword {} is not supported", k)));
+    }
+    if let Some(t) = s.get("type") {
+        let names: Vec<&JsonValue> = match t {
+            JsonValue::Array(ts) => ts.iter().collect(),
+            t => vec![t],
+        };
+        for name in names {
+            let ok = name.as_str().is_some_and(|n| {
+                ["null", "boolean", "object", "array", "string", "number", "integer"].contains(&n)
+            });
+            if !ok {
+                return Err(invalid(format!("unknown type {}", name)));
+            }
+        }
+    }
+    if let Some(p) = s.get("pattern") {
+        let p = p.as_str().ok_or_else(|| invalid("pattern must be a string".to_string()))?;
+        let re = Regex::new(p).map_err(|e| invalid(format!("bad pattern: {}", e)))?;
+        patterns.insert(p.to_string(), re);
+    }
+    for k in ["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum", "multipleOf"] {
+        if s.get(k).is_some_and(|v| !v.is_number()) {
+            return Err(invalid(format!("{} must be a number", k)));
+        }
+    }
+    for k in ["minLength", "maxLength", "minItems", "maxItems", "minProperties", "maxProperties"] {
+        if s.get(k).is_some_and(|v| !v.is_u64()) {
+            return Err(invalid(format!("{} must be a non-negative integer", k)));
+        }
+    }
+    if s.get("required").is_some_and(|r| !r.as_array().is_some_and(|a| a.iter().all(|k| k.is_string()))) {
+        return Err(invalid("required must be an array of strings".to_string()));
+    }
+
+    for k in ["items", "additionalProperties", "not"] {
+        if let Some(sub) = s.get(k) {
+            check(sub, &format!("{}/{}", at, k), patterns)?;
+        }
+    }
+    if let Some(props) = s.get("properties") {
+        let props = props.as_object().ok_or_else(|| invalid("properties must be an object".to_string()))?;
+        for (name, sub) in props {
+            check(sub, &format!("{}/properties/{}", at, escape(name)), patterns)?;
+        }
+    }
+    for k in ["prefixItems", "allOf", "anyOf", "oneOf"] {
+        if let Some(list) = s.get(k) {
+            let list = list.as_array().ok_or_else(|| invalid(format!("{} must be an array", k)))?;
+            for (i, sub) in list.iter().enumerate() {
+                check(sub, &format!("{}/{}/{}", at, k, i), patterns)?;
+            }
+        }
+    }
+    Ok(())
+}
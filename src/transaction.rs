@@ -1,241 +1,953 @@
-use tikv_rust::{Transaction, Key, Value, KvPair, BoundRange};
+use std::sync::Arc;
+use tikv_rust::{BoundRange, Transaction, Key, Value, TimestampExt};
 use serde_json::{Value as JsonValue, Map};
-use crate::errors::OsunbitDBError;
-use crate::utils::{set_deep, get_deep, remove_deep};
+use std::collections::HashMap;
+use std::ops::{Bound, Index};
+use crate::errors::{OsunbitDBError, ResultExt};
+use crate::filter::Filter;
+use crate::group::{self, CollectionGroup, GroupResult};
+use crate::projection::FieldMask;
+use crate::aggregate::{self, AggregateResult, Aggregation};
+use crate::blob;
+use crate::chunk::{self, Manifest};
+use crate::codec::{decode_doc, encode_doc, is_current, stored_version};
+use crate::config::DbConfig;
+use crate::validation::Violation;
+use crate::scan::{self, CollectionStats, Direction, Reader, ScanOptions, ScanResult};
+use crate::ops::{is_server_timestamp, FieldOp, IntoUpdate, Update};
+use crate::ids::{counter_id, IdGenerator};
+use crate::utils::{meta_key, set_deep, get_deep, remove_deep};
 use serde_json::json;
 
+/// Fields written for collections with `CollectionConfig::timestamps` on.
+pub const CREATED_AT: &str = "createdAt";
+pub const UPDATED_AT: &str = "updatedAt";
+
+/// Page size used when `update_where` walks a collection.
+const UPDATE_WHERE_PAGE: u32 = 500;
+
+/// Document state around a write, returned by the `*_returning` variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteResult {
+    /// Document before the write, `None` if it did not exist.
+    pub before: Option<JsonValue>,
+    /// Document after the write, `None` after a delete.
+    pub after: Option<JsonValue>,
+}
+
+/// Outcome of `batch_get`, every list in the order the ids were requested.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchGetResult {
+    /// Documents that exist, as `(id, document)`.
+    pub found: Vec<(String, JsonValue)>,
+    /// Ids with no stored document.
+    pub missing: Vec<String>,
+    /// Entries of the ids array that were not strings or not valid ids (see `Limits`).
+    pub invalid: Vec<JsonValue>,
+    /// Ids whose stored value could not be decoded, with the decode error.
+    pub corrupt: Vec<(String, String)>,
+}
+
+impl BatchGetResult {
+    pub fn get(&self, id: &str) -> Option<&JsonValue> {
+        self.found.iter().find(|(k, _)| k == id).map(|(_, v)| v)
+    }
+
+    /// True when every requested id was found and decoded.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty() && self.corrupt.is_empty()
+    }
+
+    /// Found documents as a `{id: document}` object (the previous `batch_get` shape).
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.found.iter().cloned().collect())
+    }
+}
+
+/// `result["id"]` returns the document, or `Null` when it was not found.
+impl Index<&str> for BatchGetResult {
+    type Output = JsonValue;
+
+    fn index(&self, id: &str) -> &JsonValue {
+        static NULL: JsonValue = JsonValue::Null;
+        self.get(id).unwrap_or(&NULL)
+    }
+}
 
 pub struct TransactionHandle {
     pub(crate) tx: Transaction,
+    pub(crate) config: Arc<DbConfig>,
+    /// Chunk count of every document key this transaction has read or
+    /// written (0 for plain or missing values), so writes and deletes only
+    /// fetch the stored manifest for keys they have not seen.
+    chunks: HashMap<Key, u32>,
 }
 
 impl TransactionHandle {
+    pub(crate) fn new(tx: Transaction, config: Arc<DbConfig>) -> Self {
+        TransactionHandle { tx, config, chunks: HashMap::new() }
+    }
+
     fn key(base: &str, id: &str) -> Key {
         let key = format!("{}:{}", base, id);
         Key::from(key)
     }
 
     pub async fn add(&mut self, collection: &str, id: &str, value: &JsonValue) -> Result<(), OsunbitDBError> {
-        let bytes = serde_json::to_vec(value)?;
-        self.tx.put(Self::key(collection, id), Value::from(bytes)).await?;
-        Ok(())
+        async {
+            self.config.limits.check_key(collection, id)?;
+            let before = if self.timestamps(collection) { self.get(collection, id).await? } else { None };
+            let value = self.new_doc(value);
+            self.write(collection, id, value, before.as_ref()).await?;
+            Ok(())
+        }
+        .await
+        .with_context("add", collection, Some(id))
     }
 
-    pub async fn get(&mut self, collection: &str, id: &str) -> Result<Option<JsonValue>, OsunbitDBError> {
-        let bytes_opt = self.tx.get(Self::key(collection, id)).await?;
-        if let Some(bytes) = bytes_opt {
-            let json: JsonValue = serde_json::from_slice(&bytes)?;
-            Ok(Some(json))
+    /// Like `add`, but fails with `AlreadyExists` instead of replacing a stored
+    /// document. The key is locked, so of two concurrent creates only one wins.
+    pub async fn create(&mut self, collection: &str, id: &str, value: &JsonValue) -> Result<(), OsunbitDBError> {
+        async {
+            self.config.limits.check_key(collection, id)?;
+            if self.tx.get_for_update(Self::key(collection, id)).await?.is_some() {
+                return Err(OsunbitDBError::AlreadyExists {
+                    collection: collection.to_string(),
+                    id: id.to_string(),
+                    context: None,
+                });
+            }
+            let value = self.new_doc(value);
+            self.write(collection, id, value, None).await?;
+            Ok(())
+        }
+        .await
+        .with_context("create", collection, Some(id))
+    }
+
+    /// Timestamp shared by every write of this transaction, in ms since epoch.
+    /// Taken from the start timestamp issued by PD, not the local clock.
+    pub fn write_time(&self) -> i64 {
+        self.tx.start_timestamp().physical
+    }
+
+    fn timestamps(&self, collection: &str) -> bool {
+        self.config.collection(collection).is_some_and(|c| c.timestamps)
+    }
+
+    /// A document passed to `add` and friends, with its `server_timestamp()`
+    /// placeholders resolved.
+    fn new_doc(&self, value: &JsonValue) -> JsonValue {
+        let mut value = value.clone();
+        resolve_server_timestamps(&mut value, self.write_time());
+        value
+    }
+
+    /// Stamp timestamps and store the document. `before` is the stored
+    /// document, used to keep `createdAt`.
+    async fn write(
+        &mut self,
+        collection: &str,
+        id: &str,
+        mut value: JsonValue,
+        before: Option<&JsonValue>,
+    ) -> Result<JsonValue, OsunbitDBError> {
+        let now = self.write_time();
+        if self.timestamps(collection) {
+            if let JsonValue::Object(obj) = &mut value {
+                if !obj.contains_key(CREATED_AT) {
+                    let created = before
+                        .and_then(|b| b.get(CREATED_AT))
+                        .cloned()
+                        .unwrap_or(JsonValue::from(now));
+                    obj.insert(CREATED_AT.to_string(), created);
+                }
+                obj.insert(UPDATED_AT.to_string(), JsonValue::from(now));
+            }
+        }
+
+        // Limits first: schema validation walks the whole document
+        self.config.limits.check_document(&value)?;
+
+        #[cfg(feature = "schema")]
+        if let Some(schema) = self.config.collection(collection).and_then(|c| c.schema.as_ref()) {
+            let mut violations = schema.validate(&value);
+            if !violations.is_empty() {
+                for v in violations.iter_mut() {
+                    v.id = id.to_string();
+                }
+                return Err(OsunbitDBError::Validation(violations));
+            }
+        }
+
+        self.put_doc(collection, id, &value).await?;
+        Ok(value)
+    }
+
+    /// Encode with the collection's settings and store, no stamping.
+    async fn put_doc(&mut self, collection: &str, id: &str, value: &JsonValue) -> Result<(), OsunbitDBError> {
+        let bytes = encode_doc(&self.config, collection, id, value)?;
+        self.put_value(Self::key(collection, id), bytes).await
+    }
+
+    /// Store `bytes` at `key`, split into chunks above the chunk size, and
+    /// delete chunks of the previous value that are no longer used.
+    async fn put_value(&mut self, key: Key, bytes: Vec<u8>) -> Result<(), OsunbitDBError> {
+        let old = self.stored_chunks(&key).await?;
+        let size = self.config.chunk_size();
+        let count = if bytes.len() > size {
+            let (manifest, chunks) = chunk::split(&bytes, size);
+            let count = chunks.len() as u32;
+            for (i, part) in chunks.into_iter().enumerate() {
+                self.tx.put(chunk::chunk_key(&key, i as u32), part).await?;
+            }
+            self.tx.put(key.clone(), manifest).await?;
+            count
         } else {
-            Ok(None)
+            self.tx.put(key.clone(), Value::from(bytes)).await?;
+            0
+        };
+        for i in count..old {
+            self.tx.delete(chunk::chunk_key(&key, i)).await?;
         }
+        self.chunks.insert(key, count);
+        Ok(())
     }
 
-    pub async fn delete(&mut self, collection: &str, id: &str) -> Result<(), OsunbitDBError> {
-        self.tx.delete(Self::key(collection, id)).await?;
+    /// Number of chunks the value at `key` is split into, 0 if it is not.
+    /// Only reads the key when this transaction has not seen it yet.
+    async fn stored_chunks(&mut self, key: &Key) -> Result<u32, OsunbitDBError> {
+        if let Some(count) = self.chunks.get(key) {
+            return Ok(*count);
+        }
+        let stored = self.tx.get(key.clone()).await?;
+        Ok(self.note_chunks(key.clone(), stored.as_deref()))
+    }
+
+    /// Remember how `value`, as stored at `key`, is chunked.
+    fn note_chunks(&mut self, key: Key, value: Option<&[u8]>) -> u32 {
+        let count = value.and_then(Manifest::parse).map_or(0, |m| m.count);
+        self.chunks.insert(key, count);
+        count
+    }
+
+    /// Learn how the values at `keys` are chunked with a single read, ahead
+    /// of writing them blind.
+    async fn prefetch_chunks(&mut self, keys: Vec<Key>) -> Result<(), OsunbitDBError> {
+        let keys: Vec<Key> = keys.into_iter().filter(|k| !self.chunks.contains_key(k)).collect();
+        let found: HashMap<Key, Value> = self.tx.batch_get(keys.clone()).await?.map(|kv| kv.into()).collect();
+        for key in keys {
+            let value = found.get(&key).map(Vec::as_slice);
+            self.note_chunks(key, value);
+        }
         Ok(())
     }
 
+    pub async fn get(&mut self, collection: &str, id: &str) -> Result<Option<JsonValue>, OsunbitDBError> {
+        async {
+            self.config.limits.check_key(collection, id)?;
+            let key = Self::key(collection, id);
+            let stored = self.tx.get(key.clone()).await?;
+            self.note_chunks(key.clone(), stored.as_deref());
+            let Some(bytes) = stored else {
+                return Ok(None);
+            };
+            let mut page = [(key, Some(bytes))];
+            Reader::Tx(&mut self.tx).reassemble(&mut page).await?;
+            let bytes = page[0].1.take().unwrap_or_default();
+            Ok(Some(decode_doc(&self.config, collection, id, &bytes)?))
+        }
+        .await
+        .with_context("get", collection, Some(id))
+    }
 
-pub async fn update(
-    &mut self,
-    collection: &str,
-    id: &str,
-    fields: &JsonValue,
-) -> Result<(), OsunbitDBError> {
-    let mut data = self.get(collection, id).await?.unwrap_or(JsonValue::Object(Map::new()));
+    /// Check whether a document exists without reading its value.
+    pub async fn exists(&mut self, collection: &str, id: &str) -> Result<bool, OsunbitDBError> {
+        async {
+            self.config.limits.check_key(collection, id)?;
+            scan::exists(&mut Reader::Tx(&mut self.tx), collection, id).await
+        }
+        .await
+        .with_context("exists", collection, Some(id))
+    }
 
-    if let JsonValue::Object(ref mut obj) = data {
-        if let JsonValue::Object(new_fields) = fields {
-            for (k, v) in new_fields {
-                if let Some(op) = v.get("__op") {
-                    match op.as_str().unwrap_or("") {
-                        "inc" => {
-                            let amount_val = &v["amount"];
-
-                            // Read existing value (can be i64 or f64)
-                            let existing_val = get_deep(obj, k);
-
-                            let new_val = match (existing_val, amount_val) {
-                                // Both integers
-                                (Some(e), a) if e.is_i64() && a.is_i64() => {
-                                    JsonValue::from(e.as_i64().unwrap() + a.as_i64().unwrap())
-                                }
-
-                                // Anything involving floats → convert to f64
-                                (Some(e), a) => {
-                                    let current = e.as_f64().unwrap_or(0.0);
-                                    let delta = a.as_f64().unwrap_or(0.0);
-                                    JsonValue::from(current + delta)
-                                }
-
-                                // No existing value → just use amount directly
-                                (None, a) => a.clone(),
-                            };
-
-                            set_deep(obj, k, new_val);
-                        }
-                        "remove" => {
-                            remove_deep(obj, k);
-                        }
-                        "array_union" => {
-                            let new_vals = v["values"].as_array().cloned().unwrap_or_default();
-                            let mut existing = get_deep(obj, k)
-                                .and_then(|val| val.as_array().cloned())
-                                .unwrap_or_default();
-
-                            for nv in new_vals {
-                                if !existing.contains(&nv) {
-                                    existing.push(nv);
-                                }
-                            }
-
-                            set_deep(obj, k, JsonValue::Array(existing));
-                        }
-                        "array_remove" => {
-                            let rem_vals = v["values"].as_array().cloned().unwrap_or_default();
-                            let mut existing = get_deep(obj, k)
-                                .and_then(|val| val.as_array().cloned())
-                                .unwrap_or_default();
+    /// Number of documents in the range of `opts`, see `OsunbitDB::count`.
+    pub async fn count(&mut self, collection: &str, opts: &ScanOptions) -> Result<u64, OsunbitDBError> {
+        scan::count(&mut Reader::Tx(&mut self.tx), &self.config, collection, opts).await
+            .with_context("count", collection, None)
+    }
 
-                            existing.retain(|item| !rem_vals.contains(item));
+    /// Document count, bytes, id range and largest document of `collection`.
+    pub async fn stats(&mut self, collection: &str) -> Result<CollectionStats, OsunbitDBError> {
+        async {
+            self.config.limits.check_collection(collection)?;
+            scan::stats(&mut Reader::Tx(&mut self.tx), collection).await
+        }
+        .await
+        .with_context("stats", collection, None)
+    }
 
-                            set_deep(obj, k, JsonValue::Array(existing));
-                        }
-                        _ => {
-                            set_deep(obj, k, v.clone());
-                        }
-                    }
-                } else {
-                    set_deep(obj, k, v.clone());
+    /// Run `sum`/`avg`/`min`/`max`/`count` over the collection, see `Aggregation`.
+    pub async fn aggregate(&mut self, collection: &str, agg: &Aggregation) -> Result<AggregateResult, OsunbitDBError> {
+        aggregate::aggregate(&mut Reader::Tx(&mut self.tx), &self.config, collection, agg).await
+            .with_context("aggregate", collection, None)
+    }
+
+    /// Query every collection whose last path segment is `query.name`.
+    pub async fn collection_group(&mut self, query: &CollectionGroup) -> Result<GroupResult, OsunbitDBError> {
+        group::collection_group(&mut Reader::Tx(&mut self.tx), &self.config, query).await
+            .with_context("collection_group", &query.name, None)
+    }
+
+    /// Direct child collection names under `parent` (`""` for the root).
+    pub async fn list_collections(&mut self, parent: &str) -> Result<Vec<String>, OsunbitDBError> {
+        async {
+            self.config.limits.check_parent(parent)?;
+            group::list_collections(&mut Reader::Tx(&mut self.tx), parent).await
+        }
+        .await
+        .with_context("list_collections", parent, None)
+    }
+
+    /// `get` trimmed to the paths in `mask`.
+    pub async fn get_masked(&mut self, collection: &str, id: &str, mask: &FieldMask) -> Result<Option<JsonValue>, OsunbitDBError> {
+        async {
+            Ok(self.get(collection, id).await?.map(|doc| mask.apply(&doc)))
+        }
+        .await
+        .with_context("get_masked", collection, Some(id))
+    }
+
+    pub async fn delete(&mut self, collection: &str, id: &str) -> Result<(), OsunbitDBError> {
+        async {
+            self.config.limits.check_key(collection, id)?;
+            let key = Self::key(collection, id);
+            let marker = blob::marker_key(collection, id);
+            // One read for the blob marker and, unless already known, the manifest
+            let mut keys = vec![marker.clone()];
+            if !self.chunks.contains_key(&key) {
+                keys.push(key.clone());
+            }
+            let mut found: HashMap<Key, Value> = self.tx.batch_get(keys).await?.map(|kv| kv.into()).collect();
+            let chunks = match self.chunks.get(&key) {
+                Some(count) => *count,
+                None => self.note_chunks(key.clone(), found.remove(&key).as_deref()),
+            };
+            for i in 0..chunks {
+                self.tx.delete(chunk::chunk_key(&key, i)).await?;
+            }
+            self.tx.delete(key.clone()).await?;
+            self.chunks.insert(key, 0);
+            if found.contains_key(&marker) {
+                self.delete_blobs(collection, id).await?;
+            }
+            Ok(())
+        }
+        .await
+        .with_context("delete", collection, Some(id))
+    }
+
+    /// Delete a document with every document in its subcollections, at any
+    /// depth, and all their blobs. Everything goes in this one transaction,
+    /// so very large subtrees can exceed TiKV's transaction size limit.
+    /// Returns the number of documents removed.
+    pub async fn delete_recursive(&mut self, collection: &str, id: &str) -> Result<u64, OsunbitDBError> {
+        async {
+            self.config.limits.check_key(collection, id)?;
+            let prefix = format!("{}:{}:", collection, id);
+            let range = BoundRange {
+                from: Bound::Included(Key::from(prefix.clone())),
+                to: Bound::Excluded(Key::from(format!("{}:{};", collection, id))),
+            };
+            let mut nested = Vec::new();
+            Reader::Tx(&mut self.tx)
+                .walk(range, Direction::Ascending, true, scan::MAX_SCAN_LIMIT, |key, _| {
+                    nested.push(String::from_utf8_lossy((&key).into()).to_string());
+                    Ok(true)
+                })
+                .await?;
+
+            let mut removed = u64::from(self.tx.key_exists(Self::key(collection, id)).await?);
+            self.delete(collection, id).await?;
+            for key in nested {
+                if let Some((sub, sub_id)) = key.rsplit_once(':') {
+                    self.delete(sub, sub_id).await?;
+                    removed += 1;
                 }
             }
-        } else {
-            return Err(OsunbitDBError::InvalidUpdate(
-                "update fields must be an object".to_string(),
-            ));
+            Ok(removed)
         }
+        .await
+        .with_context("delete_recursive", collection, Some(id))
     }
 
-    // Persist the updated document
-    self.add(collection, id, &data).await?;
 
-    Ok(())
-}
+    pub async fn update(
+        &mut self,
+        collection: &str,
+        id: &str,
+        fields: impl IntoUpdate,
+    ) -> Result<(), OsunbitDBError> {
+        async {
+            self.update_returning(collection, id, fields).await?;
+            Ok(())
+        }
+        .await
+        .with_context("update", collection, Some(id))
+    }
+
+    /// Add under a generated id and return it. Uses the collection's
+    /// `IdGenerator` (random 20 chars by default).
+    pub async fn add_auto(&mut self, collection: &str, value: &JsonValue) -> Result<String, OsunbitDBError> {
+        async {
+            let generator = self
+                .config
+                .collection(collection)
+                .map(|c| c.id_generator.clone())
+                .unwrap_or_default();
+            self.add_auto_with(collection, value, &generator).await
+        }
+        .await
+        .with_context("add_auto", collection, None)
+    }
+
+    /// `add_auto` with an explicit generator.
+    pub async fn add_auto_with(
+        &mut self,
+        collection: &str,
+        value: &JsonValue,
+        generator: &IdGenerator,
+    ) -> Result<String, OsunbitDBError> {
+        async {
+            let id = match generator.generate() {
+                Some(id) => id,
+                None => self.next_counter(collection).await?,
+            };
+            self.add(collection, &id, value).await?;
+            Ok(id)
+        }
+        .await
+        .with_context("add_auto_with", collection, None)
+    }
+
+    /// Lock and bump the collection counter; concurrent transactions wait on the lock.
+    async fn next_counter(&mut self, collection: &str) -> Result<String, OsunbitDBError> {
+        let key = meta_key("counter", collection);
+        let current = match self.tx.get_for_update(key.clone()).await? {
+            Some(bytes) => String::from_utf8_lossy(&bytes).parse::<u64>().map_err(|_| {
+                OsunbitDBError::corruption(format!("counter for {} is corrupt", collection))
+            })?,
+            None => 0,
+        };
+        let next = current + 1;
+        self.tx.put(key, next.to_string().into_bytes()).await?;
+        Ok(counter_id(next))
+    }
+
+    /// Same as `add`, but returns the document that was replaced (if any) and the new one.
+    pub async fn add_returning(
+        &mut self,
+        collection: &str,
+        id: &str,
+        value: &JsonValue,
+    ) -> Result<WriteResult, OsunbitDBError> {
+        async {
+            let before = self.get(collection, id).await?;
+            let value = self.new_doc(value);
+            let after = self.write(collection, id, value, before.as_ref()).await?;
+            Ok(WriteResult { before, after: Some(after) })
+        }
+        .await
+        .with_context("add_returning", collection, Some(id))
+    }
+
+    /// Same as `update`, but returns the document before and after the update,
+    /// both read inside this transaction.
+    pub async fn update_returning(
+        &mut self,
+        collection: &str,
+        id: &str,
+        fields: impl IntoUpdate,
+    ) -> Result<WriteResult, OsunbitDBError> {
+        async {
+            let update = fields.into_update()?;
+            let before = self.get(collection, id).await?;
+            let after = apply_update(before.clone(), &update, self.write_time());
+
+            // Persist the updated document
+            let after = self.write(collection, id, after, before.as_ref()).await?;
+
+            Ok(WriteResult { before, after: Some(after) })
+        }
+        .await
+        .with_context("update_returning", collection, Some(id))
+    }
+
+    /// Same as `delete`, but returns the document that was deleted (if any).
+    pub async fn delete_returning(&mut self, collection: &str, id: &str) -> Result<WriteResult, OsunbitDBError> {
+        async {
+            let before = self.get(collection, id).await?;
+            self.delete(collection, id).await?;
+            Ok(WriteResult { before, after: None })
+        }
+        .await
+        .with_context("delete_returning", collection, Some(id))
+    }
+
+    /// Commit when `res` is `Ok`, otherwise roll back and return the error.
+    pub(crate) async fn finish<T>(self, res: Result<T, OsunbitDBError>) -> Result<T, OsunbitDBError> {
+        match res {
+            Ok(value) => {
+                self.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self.rollback().await;
+                Err(e)
+            }
+        }
+    }
 
     pub async fn commit(mut self) -> Result<(), OsunbitDBError> {
-        self.tx.commit().await?;
+        self.tx.commit().await.map_err(|e| OsunbitDBError::from(e).with_context("commit", "", None))?;
         Ok(())
     }
 
    pub async fn rollback(mut self) -> Result<(), OsunbitDBError> {
-    self.tx.rollback().await?;
+    self.tx.rollback().await.map_err(|e| OsunbitDBError::from(e).with_context("rollback", "", None))?;
     Ok(())
 }
-pub async fn scan(
-    &mut self,
-    collection: &str,
-    limit: u32,
-    cursor: &str,
-    order: &str,
-) -> Result<JsonValue, OsunbitDBError> {
-    let prefix = format!("{}:", collection);
-    let reverse = order.eq_ignore_ascii_case("d");
-
-    let lowest = Key::from(format!("{}:", collection));
-    let highest = Key::from(format!("{}:\u{10FFFF}", collection));
-
-    let range: BoundRange = if reverse {
-        let upper = if cursor.is_empty() {
-            highest.clone()
-        } else {
-            Key::from(format!("{}:{}", collection, cursor)) 
-        };
-        (lowest.clone()..upper.clone()).into()
-    } else {
-        let start = if cursor.is_empty() {
-            lowest.clone()
-        } else {
-            Key::from(format!("{}:{}\u{0000}", collection, cursor))
-        };
-        (start.clone()..=highest.clone()).into()
-    };
-
-let kvs: Vec<KvPair> = if reverse {
-    self.tx.scan_reverse(range, limit).await?.collect()
-} else {
-    self.tx.scan(range, limit).await?.collect()
-};
+/// Legacy page scan: `cursor` is the last id of the previous page (exclusive),
+    /// `order` is "a" or "d". Returns a `{id: document}` object.
+    pub async fn scan(
+        &mut self,
+        collection: &str,
+        limit: u32,
+        cursor: &str,
+        order: &str,
+    ) -> Result<JsonValue, OsunbitDBError> {
+        async {
+            let direction = Direction::parse(order)?;
+            let mut opts = ScanOptions::new().limit(limit).direction(direction);
+            if !cursor.is_empty() {
+                opts = match direction {
+                    Direction::Ascending => opts.start_after(cursor),
+                    Direction::Descending => opts.end_before(cursor),
+                };
+            }
 
-    let mut out = serde_json::Map::new();
-    for kv in kvs {
-        let k = String::from_utf8_lossy(kv.key().as_ref().into()).to_string();
-        let doc_id = k.strip_prefix(&prefix).unwrap_or(&k).to_string();
-        let v = serde_json::from_slice(&kv.value().to_vec()).unwrap_or(JsonValue::Null);
-        out.insert(doc_id, v);
+            let result = self.scan_with(collection, &opts).await?;
+            Ok(JsonValue::Object(result.items.into_iter().collect()))
+        }
+        .await
+        .with_context("scan", collection, None)
     }
 
-    Ok(JsonValue::Object(out))
-}
+    /// Scan a bounded id range of `collection`, see `ScanOptions`.
+    pub async fn scan_with(&mut self, collection: &str, opts: &ScanOptions) -> Result<ScanResult, OsunbitDBError> {
+        async {
+            let ts = self.tx.start_timestamp().version();
+            scan::scan_with(Reader::Tx(&mut self.tx), &self.config, collection, opts, ts).await
+        }
+        .await
+        .with_context("scan_with", collection, None)
+    }
 
 pub async fn batch_add(&mut self, collection: &str, items_json: &JsonValue) -> Result<(), OsunbitDBError> {
-        if let JsonValue::Object(map) = items_json {
-            for (id, value) in map {
-                self.add(collection, id, value).await?;
+        async {
+            if let JsonValue::Object(map) = items_json {
+                self.config.limits.check_collection(collection)?;
+                for id in map.keys() {
+                    self.config.limits.check_id(id)?;
+                }
+                // One read for every createdAt to keep
+                let current = if self.timestamps(collection) {
+                    let ids = JsonValue::Array(map.keys().map(|id| json!(id)).collect());
+                    self.batch_get(collection, &ids).await?
+                } else {
+                    self.prefetch_chunks(map.keys().map(|id| Self::key(collection, id)).collect()).await?;
+                    BatchGetResult::default()
+                };
+                let mut violations = Vec::new();
+                for (id, value) in map {
+                    let value = self.new_doc(value);
+                    let res = self.write(collection, id, value, current.get(id)).await;
+                    collect_violations(res.with_context("batch_add", collection, Some(id)), &mut violations)?;
+                }
+                if !violations.is_empty() {
+                    return Err(OsunbitDBError::Validation(violations));
+                }
+            } else {
+                return Err(OsunbitDBError::invalid_update("batch_add expects a JSON object".to_string()));
             }
-        } else {
-            return Err(OsunbitDBError::InvalidUpdate("batch_add expects a JSON object".to_string()));
+            Ok(())
         }
-        Ok(())
+        .await
+        .with_context("batch_add", collection, None)
+    }
+
+    /// Fetch many ids at once. The result keeps the input order and reports
+    /// missing ids, non-string ids and values that could not be decoded.
+    pub async fn batch_get(
+        &mut self,
+        collection: &str,
+        ids_json: &JsonValue,
+    ) -> Result<BatchGetResult, OsunbitDBError> {
+        async {
+            let arr = ids_json.as_array().ok_or_else(|| {
+                OsunbitDBError::invalid_update("batch_get expects a JSON array of ids".to_string())
+            })?;
+
+            self.config.limits.check_collection(collection)?;
+            let mut result = BatchGetResult::default();
+            let mut ids: Vec<&str> = Vec::with_capacity(arr.len());
+            for id_val in arr {
+                match id_val.as_str() {
+                    Some(id) if ids.contains(&id) => {}
+                    Some(id) if self.config.limits.check_id(id).is_ok() => ids.push(id),
+                    _ => result.invalid.push(id_val.clone()),
+                }
+            }
+
+            let keys: Vec<Key> = ids.iter().map(|id| Self::key(collection, id)).collect();
+            let mut found: HashMap<Key, Value> = self.tx.batch_get(keys.clone()).await?.map(|kv| kv.into()).collect();
+            let mut page = Vec::with_capacity(found.len());
+            for key in keys {
+                let value = found.remove(&key);
+                self.note_chunks(key.clone(), value.as_deref());
+                if value.is_some() {
+                    page.push((key, value));
+                }
+            }
+            Reader::Tx(&mut self.tx).reassemble(&mut page).await?;
+            let mut values: HashMap<Vec<u8>, Value> = page
+                .into_iter()
+                .filter_map(|(k, v)| Some((Vec::from(k), v?)))
+                .collect();
+
+            for id in ids {
+                let key: Vec<u8> = Self::key(collection, id).into();
+                match values.remove(&key) {
+                    Some(bytes) => match decode_doc(&self.config, collection, id, &bytes) {
+                        Ok(doc) => result.found.push((id.to_string(), doc)),
+                        Err(e) => result.corrupt.push((id.to_string(), e.to_string())),
+                    },
+                    None => result.missing.push(id.to_string()),
+                }
+            }
+            Ok(result)
+        }
+        .await
+        .with_context("batch_get", collection, None)
     }
 
- pub async fn batch_get(
-    &mut self,
-    collection: &str,
-    ids_json: &JsonValue,
-) -> Result<JsonValue, OsunbitDBError> {
-    let mut out = serde_json::Map::new();
-
-    if let JsonValue::Array(arr) = ids_json {
-        // Build all keys
-        let keys: Vec<Key> = arr
-            .iter()
-            .filter_map(|id_val| id_val.as_str())
-            .map(|id| Key::from(format!("{}:{}", collection, id)))
-            .collect();
-
-        // ✅ Collect iterator to Vec
-        let kv_iter = self.tx.batch_get(keys).await?;
-        let kvs: Vec<KvPair> = kv_iter.collect();
-
-        for kv in kvs {
-             let full_key = String::from_utf8_lossy(kv.key().as_ref().into()).to_string();
-            let doc_id = full_key.strip_prefix(&format!("{}:", collection)).unwrap_or(&full_key);
-            let v = serde_json::from_slice(kv.value().as_ref()).unwrap_or(json!(null));
-            out.insert(doc_id.to_string(), v);
-        }
-        Ok(JsonValue::Object(out))
-    } else {
-        Err(OsunbitDBError::InvalidUpdate(
-            "batch_get expects a JSON array of ids".to_string(),
-        ))
+    /// `batch_get` with every found document trimmed to the paths in `mask`.
+    pub async fn batch_get_masked(
+        &mut self,
+        collection: &str,
+        ids_json: &JsonValue,
+        mask: &FieldMask,
+    ) -> Result<BatchGetResult, OsunbitDBError> {
+        async {
+            let mut result = self.batch_get(collection, ids_json).await?;
+            for (_, doc) in result.found.iter_mut() {
+                *doc = mask.apply(doc);
+            }
+            Ok(result)
+        }
+        .await
+        .with_context("batch_get_masked", collection, None)
     }
-}
 
+    /// Update many documents in one pass: `{id: fields}`, each `fields` in the same
+    /// form `update` takes. Current state comes from a single `batch_get`, missing
+    /// documents are created (upsert).
+    pub async fn batch_update(&mut self, collection: &str, updates_json: &JsonValue) -> Result<(), OsunbitDBError> {
+        async {
+            let map = updates_json.as_object().ok_or_else(|| {
+                OsunbitDBError::invalid_update("batch_update expects a JSON object".to_string())
+            })?;
+
+            self.config.limits.check_collection(collection)?;
+            let mut updates = Vec::with_capacity(map.len());
+            for (id, fields) in map {
+                self.config.limits.check_id(id)?;
+                updates.push((id, fields.into_update().with_context("batch_update", collection, Some(id))?));
+            }
 
+            let ids = JsonValue::Array(map.keys().map(|id| json!(id)).collect());
+            let current = self.batch_get(collection, &ids).await?;
+            if let Some((id, err)) = current.corrupt.first() {
+                return Err(OsunbitDBError::corruption(format!(
+                    "batch_update cannot decode document {}: {}", id, err
+                )));
+            }
 
+            let mut violations = Vec::new();
+            for (id, update) in updates {
+                let after = apply_update(current.get(id).cloned(), &update, self.write_time());
+                let res = self.write(collection, id, after, current.get(id)).await;
+                collect_violations(res.with_context("batch_update", collection, Some(id)), &mut violations)?;
+            }
+            if !violations.is_empty() {
+                return Err(OsunbitDBError::Validation(violations));
+            }
+            Ok(())
+        }
+        .await
+        .with_context("batch_update", collection, None)
+    }
+
+    /// Apply `fields` to every document in `collection` matching `filter`.
+    /// Returns the ids that were updated.
+    pub async fn update_where(
+        &mut self,
+        collection: &str,
+        filter: &Filter,
+        fields: impl IntoUpdate,
+    ) -> Result<Vec<String>, OsunbitDBError> {
+        async {
+            let update = fields.into_update()?;
+            let mut updated = Vec::new();
+            let mut violations = Vec::new();
+            let mut opts = ScanOptions::new().limit(UPDATE_WHERE_PAGE);
+
+            loop {
+                let page = self.scan_with(collection, &opts).await?;
+
+                for (id, doc) in &page.items {
+                    if filter.matches(doc) {
+                        let after = apply_update(Some(doc.clone()), &update, self.write_time());
+                        let res = self.write(collection, id, after, Some(doc)).await;
+                        collect_violations(res.with_context("update_where", collection, Some(id)), &mut violations)?;
+                        updated.push(id.clone());
+                    }
+                }
+
+                match page.last_id() {
+                    Some(last) if page.len() == UPDATE_WHERE_PAGE as usize => opts = opts.start_after(last),
+                    _ => break,
+                }
+            }
+            if !violations.is_empty() {
+                return Err(OsunbitDBError::Validation(violations));
+            }
+            Ok(updated)
+        }
+        .await
+        .with_context("update_where", collection, None)
+    }
+
+    /// Re-encode the next page of `collection` after the job's checkpoint and
+    /// move the checkpoint, both in this transaction. The checkpoint is locked,
+    /// so concurrent runs of the same job take turns. Returns how many values
+    /// changed and whether the end was reached (the checkpoint is then removed).
+    /// Values that cannot be decoded are left alone.
+    pub(crate) async fn rewrite_page(
+        &mut self,
+        collection: &str,
+        job: RewriteJob,
+        limit: u32,
+    ) -> Result<(u64, bool), OsunbitDBError> {
+        let checkpoint = meta_key(job.name(), collection);
+        let after = self
+            .tx
+            .get_for_update(checkpoint.clone())
+            .await?
+            .map(|id| String::from_utf8_lossy(&id).to_string());
+
+        let range = scan::collection_range(collection, after.as_deref().map_or(Bound::Unbounded, Bound::Excluded), Bound::Unbounded);
+        let page = Reader::Tx(&mut self.tx).scan_page(range, limit, Direction::Ascending, false).await?;
+        match page.last() {
+            Some((key, _)) if page.len() == limit as usize => {
+                let last = scan::doc_id(collection, key);
+                self.tx.put(checkpoint, last.into_bytes()).await?;
+            }
+            _ => self.tx.delete(checkpoint).await?,
+        }
+        let done = page.len() < limit as usize;
+
+        let current = self.config.collection(collection).map_or(1, |c| c.migrations.current());
+        let mut rewritten = 0;
+        for (key, value) in page {
+            let id = scan::doc_id(collection, &key);
+            // Subcollection documents follow their own collection's settings
+            if id.contains(':') {
+                continue;
+            }
+            // Blob chunks carry their own envelope and are re-sealed on their own
+            let blobs = match job {
+                RewriteJob::Rewrite => self.rewrite_blobs(collection, &id).await?,
+                RewriteJob::Migrate => 0,
+            };
+            let document = match value {
+                Some(bytes) => self.rewrite_doc(collection, &id, key, &bytes, job, current).await?,
+                None => false,
+            };
+            if document || blobs > 0 {
+                rewritten += 1;
+            }
+        }
+        Ok((rewritten, done))
+    }
+
+    /// Re-encode one document for `job` if it needs it; returns whether it did.
+    async fn rewrite_doc(
+        &mut self,
+        collection: &str,
+        id: &str,
+        key: Key,
+        bytes: &[u8],
+        job: RewriteJob,
+        current: u32,
+    ) -> Result<bool, OsunbitDBError> {
+        let outdated = match job {
+            RewriteJob::Rewrite => match is_current(&self.config, collection, id, bytes) {
+                Ok(current) => !current,
+                // Left alone, failing here would fail every later run on it
+                Err(_) => false,
+            },
+            RewriteJob::Migrate => stored_version(&self.config, collection, id, bytes).is_ok_and(|v| v < current),
+        };
+        if !outdated {
+            return Ok(false);
+        }
+        let Ok(doc) = decode_doc(&self.config, collection, id, bytes) else { return Ok(false) };
+        self.put_value(key, encode_doc(&self.config, collection, id, &doc)?).await?;
+        Ok(true)
+    }
 
     pub async fn batch_delete(&mut self, collection: &str, ids_json: &JsonValue) -> Result<(), OsunbitDBError> {
-        if let JsonValue::Array(arr) = ids_json {
-            for id_val in arr {
-                if let Some(id) = id_val.as_str() {
-                    self.delete(collection, id).await?;
+        async {
+            if let JsonValue::Array(arr) = ids_json {
+                for id_val in arr {
+                    if let Some(id) = id_val.as_str() {
+                        self.delete(collection, id).await?;
+                    }
+                }
+            } else {
+                return Err(OsunbitDBError::invalid_update("batch_delete expects a JSON array of ids".to_string()));
+            }
+            Ok(())
+        }
+        .await
+        .with_context("batch_delete", collection, None)
+    }
+
+}
+
+/// Background jobs that re-encode a whole collection, each with its own checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RewriteJob {
+    /// Anything not matching the current settings.
+    Rewrite,
+    /// Documents below the current schema version.
+    Migrate,
+}
+
+impl RewriteJob {
+    fn name(self) -> &'static str {
+        match self {
+            RewriteJob::Rewrite => "rewrite",
+            RewriteJob::Migrate => "migrate",
+        }
+    }
+}
+
+/// Keep going past schema violations so a multi-document write reports all of
+/// them at once; any other error stops it.
+fn collect_violations(res: Result<JsonValue, OsunbitDBError>, all: &mut Vec<Violation>) -> Result<(), OsunbitDBError> {
+    match res {
+        Ok(_) => Ok(()),
+        Err(OsunbitDBError::Validation(v)) => {
+            all.extend(v);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Replace every `server_timestamp()` placeholder inside objects with `now`.
+fn resolve_server_timestamps(value: &mut JsonValue, now: i64) {
+    if let JsonValue::Object(obj) = value {
+        for v in obj.values_mut() {
+            if is_server_timestamp(v) {
+                *v = JsonValue::from(now);
+            } else {
+                resolve_server_timestamps(v, now);
+            }
+        }
+    }
+}
+
+/// Apply an `Update` on top of `current`, fields in order. `now` is the value
+/// for `ServerTimestamp` fields.
+pub(crate) fn apply_update(current: Option<JsonValue>, update: &Update, now: i64) -> JsonValue {
+    let mut data = current.unwrap_or(JsonValue::Object(Map::new()));
+
+    if let JsonValue::Object(ref mut obj) = data {
+        for (k, op) in update.ops() {
+            match op {
+                FieldOp::Set(v) => {
+                    set_deep(obj, k, v.clone());
+                }
+                FieldOp::Increment(amount_val) => {
+                    // Read existing value (can be i64 or f64)
+                    let existing_val = get_deep(obj, k);
+
+                    // Both integers, unless the sum overflows
+                    let sum = existing_val
+                        .and_then(JsonValue::as_i64)
+                        .zip(amount_val.as_i64())
+                        .and_then(|(e, a)| e.checked_add(a));
+
+                    let new_val = match (existing_val, amount_val, sum) {
+                        (_, _, Some(sum)) => JsonValue::from(sum),
+
+                        // Anything involving floats or overflowing → convert to f64
+                        (Some(e), a, None) => {
+                            let current = e.as_f64().unwrap_or(0.0);
+                            let delta = a.as_f64().unwrap_or(0.0);
+                            JsonValue::from(current + delta)
+                        }
+
+                        // No existing value → just use amount directly
+                        (None, a, None) => a.clone(),
+                    };
+
+                    set_deep(obj, k, new_val);
+                }
+                FieldOp::Remove => {
+                    remove_deep(obj, k);
+                }
+                FieldOp::ArrayUnion(new_vals) => {
+                    let mut existing = get_deep(obj, k)
+                        .and_then(|val| val.as_array().cloned())
+                        .unwrap_or_default();
+
+                    for nv in new_vals {
+                        if !existing.contains(nv) {
+                            existing.push(nv.clone());
+                        }
+                    }
+
+                    set_deep(obj, k, JsonValue::Array(existing));
+                }
+                FieldOp::ServerTimestamp => {
+                    set_deep(obj, k, JsonValue::from(now));
+                }
+                FieldOp::ArrayRemove(rem_vals) => {
+                    let mut existing = get_deep(obj, k)
+                        .and_then(|val| val.as_array().cloned())
+                        .unwrap_or_default();
+
+                    existing.retain(|item| !rem_vals.contains(item));
+
+                    set_deep(obj, k, JsonValue::Array(existing));
                 }
             }
-        } else {
-            return Err(OsunbitDBError::InvalidUpdate("batch_delete expects a JSON array of ids".to_string()));
         }
-        Ok(())
     }
 
+    data
 }
@@ -2,6 +2,16 @@ use serde_json::{Value as JsonValue, Map};
 use serde::{Serialize, de::DeserializeOwned};
 use crate::errors::OsunbitDBError;
 use bincode;
+use tikv_rust::Key;
+
+/// Prefix of internal bookkeeping keys. The leading NUL keeps them out of every
+/// `collection:` range and sorts them before user keys.
+pub(crate) const META_PREFIX: &str = "\u{0}osunbit:";
+
+/// Internal key `META_PREFIX + kind:name`.
+pub(crate) fn meta_key(kind: &str, name: &str) -> Key {
+    Key::from(format!("{}{}:{}", META_PREFIX, kind, name))
+}
 
 pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, OsunbitDBError> {
     Ok(bincode::serialize(value)?)
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::io;
+#[cfg(feature = "schema")]
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use crate::errors::OsunbitDBError;
+
+/// Largest document accepted by default, as serialized JSON. Larger values
+/// are chunked, but a transaction must still fit TiKV's size limit.
+pub const DEFAULT_MAX_DOCUMENT_BYTES: usize = 16 * 1024 * 1024;
+/// Deepest nesting of objects and arrays accepted by default.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+/// Longest id (or collection path segment) accepted by default, in bytes.
+pub const DEFAULT_MAX_ID_LENGTH: usize = 512;
+
+/// One failed check, `path` is a JSON pointer into the document (`""` for the root).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Document id, filled in by the write that was rejected.
+    pub id: String,
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{} {}: {}", self.id, path, self.message)
+    }
+}
+
+/// Checks run on every `OsunbitDB` and `TransactionHandle` entry point before
+/// anything is read or written, set with `OsunbitDB::with_limits`.
+///
+/// Whatever the limits, collection paths may not be empty or have empty
+/// segments (`users::inbox`), ids may not contain `:`, and neither may
+/// contain control characters.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    max_document_bytes: usize,
+    max_depth: usize,
+    max_id_length: usize,
+    #[cfg(feature = "schema")]
+    id_pattern: Option<Regex>,
+    reserved: Vec<String>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_document_bytes: DEFAULT_MAX_DOCUMENT_BYTES,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_id_length: DEFAULT_MAX_ID_LENGTH,
+            #[cfg(feature = "schema")]
+            id_pattern: None,
+            reserved: Vec::new(),
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Largest document, measured as serialized JSON after timestamps are added.
+    pub fn max_document_bytes(mut self, bytes: usize) -> Self {
+        self.max_document_bytes = bytes;
+        self
+    }
+
+    /// Deepest nesting of objects and arrays; a flat object has depth 1.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Longest id, and longest segment of a collection path, in bytes.
+    pub fn max_id_length(mut self, bytes: usize) -> Self {
+        self.max_id_length = bytes;
+        self
+    }
+
+    /// Ids must match `pattern` as a whole, e.g. `[A-Za-z0-9_-]+`. Needs the
+    /// `schema` feature for its regex engine.
+    #[cfg(feature = "schema")]
+    pub fn id_pattern(mut self, pattern: &str) -> Result<Self, OsunbitDBError> {
+        let re = Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|e| OsunbitDBError::invalid_argument(format!("invalid id pattern {:?}: {}", pattern, e)))?;
+        self.id_pattern = Some(re);
+        Ok(self)
+    }
+
+    #[cfg(not(feature = "schema"))]
+    pub fn id_pattern(self, _pattern: &str) -> Result<Self, OsunbitDBError> {
+        Err(OsunbitDBError::invalid_argument("id patterns need the `schema` feature"))
+    }
+
+    /// Refuse `name` as an id or collection segment. A trailing `*` reserves
+    /// every name with that prefix, e.g. `__*`.
+    pub fn reserve(mut self, name: &str) -> Self {
+        self.reserved.push(name.to_string());
+        self
+    }
+
+    fn is_reserved(&self, name: &str) -> bool {
+        self.reserved.iter().any(|r| match r.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => r == name,
+        })
+    }
+
+    /// Problem with one id or path segment, if any.
+    fn name_problem(&self, name: &str) -> Option<String> {
+        if name.is_empty() {
+            Some("must not be empty".to_string())
+        } else if name.len() > self.max_id_length {
+            Some(format!("is {} bytes, the limit is {}", name.len(), self.max_id_length))
+        } else if name.chars().any(char::is_control) {
+            Some("must not contain control characters".to_string())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn check_collection(&self, collection: &str) -> Result<(), OsunbitDBError> {
+        let invalid = |reason: String| OsunbitDBError::InvalidCollection { collection: collection.to_string(), reason };
+        if collection.is_empty() {
+            return Err(invalid("must not be empty".to_string()));
+        }
+        for segment in collection.split(':') {
+            if let Some(problem) = self.name_problem(segment) {
+                return Err(invalid(format!("segment {:?} {}", segment, problem)));
+            }
+            if self.is_reserved(segment) {
+                return Err(OsunbitDBError::ReservedName(segment.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_id(&self, id: &str) -> Result<(), OsunbitDBError> {
+        let invalid = |reason: &str| OsunbitDBError::InvalidId { id: id.to_string(), reason: reason.to_string() };
+        if let Some(problem) = self.name_problem(id) {
+            return Err(invalid(&problem));
+        }
+        if id.contains(':') {
+            return Err(invalid("must not contain the ':' path separator"));
+        }
+        #[cfg(feature = "schema")]
+        if self.id_pattern.as_ref().is_some_and(|re| !re.is_match(id)) {
+            return Err(invalid("does not match the id pattern"));
+        }
+        if self.is_reserved(id) {
+            return Err(OsunbitDBError::ReservedName(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_key(&self, collection: &str, id: &str) -> Result<(), OsunbitDBError> {
+        self.check_collection(collection)?;
+        self.check_id(id)
+    }
+
+    /// `""` (the root) or a document path like `users:u1`.
+    pub(crate) fn check_parent(&self, parent: &str) -> Result<(), OsunbitDBError> {
+        match parent {
+            "" => Ok(()),
+            _ => self.check_collection(parent),
+        }
+    }
+
+    pub(crate) fn check_document(&self, doc: &JsonValue) -> Result<(), OsunbitDBError> {
+        if too_deep(doc, self.max_depth) {
+            return Err(OsunbitDBError::DocumentTooDeep { max: self.max_depth, context: None });
+        }
+        let mut size = ByteCount(0);
+        serde_json::to_writer(&mut size, doc)?;
+        if size.0 > self.max_document_bytes {
+            return Err(OsunbitDBError::DocumentTooLarge { size: size.0, max: self.max_document_bytes, context: None });
+        }
+        Ok(())
+    }
+}
+
+/// Whether `value` nests more than `max` levels, without recursing past that.
+fn too_deep(value: &JsonValue, max: usize) -> bool {
+    let mut children: Box<dyn Iterator<Item = &JsonValue>> = match value {
+        JsonValue::Object(obj) => Box::new(obj.values()),
+        JsonValue::Array(arr) => Box::new(arr.iter()),
+        _ => return false,
+    };
+    max == 0 || children.any(|v| too_deep(v, max - 1))
+}
+
+struct ByteCount(usize);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use osunbitdb::{OsunbitDB, json, Aggregation, Filter};
+
+#[tokio::test]
+async fn aggregate_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "aggregate_test:u123";
+
+    db.batch_add(col, &json!({
+        "tx1": { "amount": 100, "type": "send", "status": "success" },
+        "tx2": { "amount": 200, "type": "receive", "status": "success" },
+        "tx3": { "amount": 50,  "type": "send", "status": "pending" },
+        "tx4": { "amount": 25.5, "type": "send", "status": "success" },
+        "tx5": { "type": "receive", "status": "success" }
+    })).await?;
+    // A subcollection document in the same key range is not aggregated
+    let refunds = format!("{}:tx1:refunds", col);
+    db.add(&refunds, "r1", &json!({ "amount": 1000, "type": "send", "status": "success" })).await?;
+
+    println!("🧮 Totals over the whole collection...");
+    let res = db.aggregate(col, &Aggregation::new()
+        .count("n")
+        .sum("total", "amount")
+        .avg("avg", "amount")
+        .min("min", "amount")
+        .max("max", "amount")).await?;
+    let totals = res.values().unwrap();
+    assert_eq!(totals["n"], 5);
+    assert_eq!(totals["total"], 375.5);
+    assert_eq!(totals["avg"], 93.875);
+    assert_eq!(totals["min"], 25.5);
+    assert_eq!(totals["max"], 200);
+
+    println!("🗂️ Grouped by type, successful only...");
+    let res = db.aggregate(col, &Aggregation::new()
+        .count("n")
+        .sum("total", "amount")
+        .group_by("type")
+        .filter(Filter::eq("status", "success"))).await?;
+    println!("{}", res.to_json());
+    assert_eq!(res.groups.len(), 2);
+    assert_eq!(res.group(&json!("send")).unwrap()["total"], 125.5);
+    assert_eq!(res.group(&json!("receive")).unwrap()["n"], 2);
+    assert_eq!(res.group(&json!("receive")).unwrap()["total"], 200);
+
+    db.batch_delete(col, &json!(["tx1", "tx2", "tx3", "tx4", "tx5"])).await?;
+    db.delete(&refunds, "r1").await?;
+
+    // Empty collection without group_by still gives one row
+    let res = db.aggregate(col, &Aggregation::new().count("n").avg("avg", "amount")).await?;
+    assert_eq!(res.values().unwrap()["n"], 0);
+    assert!(res.values().unwrap()["avg"].is_null());
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use osunbitdb::{OsunbitDB, json};
+use osunbitdb::{OsunbitDB, json, increment, Filter};
 
 #[tokio::test]
 async fn batch_operations_test() -> Result<(), Box<dyn std::error::Error>> {
@@ -36,7 +36,8 @@ async fn batch_operations_test() -> Result<(), Box<dyn std::error::Error>> {
     println!("✅ Batch delete done.");
 
     let after_delete = db.batch_get("transactions:u1", &ids).await?;
-    assert!(after_delete.as_object().unwrap().is_empty());
+    assert!(after_delete.found.is_empty());
+    assert_eq!(after_delete.missing, vec!["tx1", "tx2", "tx3"]);
     println!("✅ Confirmed all items deleted.");
 
     // --------------------------------------------------------------------
@@ -81,3 +82,88 @@ async fn batch_operations_test() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n🎉 All batch + transaction tests completed successfully!");
     Ok(())
 }
+
+#[test]
+fn filter_matches_test() {
+    let doc = json!({ "amount": 150, "status": "pending", "tags": ["a", "b"], "meta": { "score": 2.5 } });
+
+    assert!(Filter::eq("status", "pending").matches(&doc));
+    assert!(Filter::gt("amount", 100).and(Filter::lte("meta.score", 2.5)).matches(&doc));
+    assert!(Filter::contains("tags", "b").matches(&doc));
+    assert!(Filter::is_in("status", ["done", "pending"]).matches(&doc));
+    assert!(Filter::eq("status", "done").or(Filter::exists("meta.score")).matches(&doc));
+    assert!(!Filter::lt("amount", 100.5).matches(&doc));
+    assert!(!Filter::eq("missing", 1).matches(&doc));
+    assert!(Filter::ne("missing", 1).matches(&doc));
+    assert!(Filter::exists("status").not().not().matches(&doc));
+}
+
+#[tokio::test]
+async fn batch_update_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+
+    db.batch_add("transactions:bu1", &json!({
+        "tx1": { "amount": 100, "status": "pending" },
+        "tx2": { "amount": 200, "status": "success" },
+        "tx3": { "amount": 50,  "status": "pending" }
+    })).await?;
+
+    println!("➕ Batch update with operators (tx4 does not exist yet → upsert)...");
+    db.batch_update("transactions:bu1", &json!({
+        "tx1": { "amount": increment(10) },
+        "tx2": { "amount": increment(-20), "status": "refunded" },
+        "tx4": { "amount": increment(5) }
+    })).await?;
+
+    let ids = json!(["tx1", "tx2", "tx3", "tx4"]);
+    let fetched = db.batch_get("transactions:bu1", &ids).await?;
+    assert_eq!(fetched["tx1"]["amount"], 110);
+    assert_eq!(fetched["tx2"]["amount"], 180);
+    assert_eq!(fetched["tx2"]["status"], "refunded");
+    assert_eq!(fetched["tx3"]["amount"], 50);
+    assert_eq!(fetched["tx4"]["amount"], 5);
+
+    println!("🔎 update_where status == pending...");
+    // Matches the filter too, but belongs to a subcollection of tx1
+    db.add("transactions:bu1:tx1:notes", "n1", &json!({ "status": "pending" })).await?;
+    let updated = db.update_where(
+        "transactions:bu1",
+        &Filter::eq("status", "pending"),
+        &json!({ "status": "success" }),
+    ).await?;
+    assert_eq!(updated, vec!["tx1".to_string(), "tx3".to_string()]);
+
+    let fetched = db.batch_get("transactions:bu1", &ids).await?;
+    assert_eq!(fetched["tx1"]["status"], "success");
+    assert_eq!(fetched["tx3"]["status"], "success");
+    assert_eq!(fetched["tx2"]["status"], "refunded");
+    assert_eq!(db.get("transactions:bu1:tx1:notes", "n1").await?.unwrap()["status"], "pending");
+
+    db.delete("transactions:bu1:tx1:notes", "n1").await?;
+    db.batch_delete("transactions:bu1", &ids).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn batch_get_report_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+
+    db.batch_add("transactions:bg1", &json!({
+        "b": { "amount": 2 },
+        "a": { "amount": 1 }
+    })).await?;
+
+    // Input order is preserved, not key order
+    let res = db.batch_get("transactions:bg1", &json!(["b", "nope", 42, "a"])).await?;
+    let found: Vec<&str> = res.found.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(found, vec!["b", "a"]);
+    assert_eq!(res.missing, vec!["nope"]);
+    assert_eq!(res.invalid, vec![json!(42)]);
+    assert!(res.corrupt.is_empty());
+    assert!(!res.is_complete());
+    assert_eq!(res["a"]["amount"], 1);
+    assert!(res["nope"].is_null());
+
+    db.batch_delete("transactions:bg1", &json!(["a", "b"])).await?;
+    Ok(())
+}
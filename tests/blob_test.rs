@@ -0,0 +1,90 @@
+use osunbitdb::{OsunbitDB, json};
+#[cfg(feature = "encryption")]
+use osunbitdb::{Cipher, CollectionConfig, StaticKeys};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn blob_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?.with_chunk_size(1000);
+    let col = "blob_test";
+    let pdf: Vec<u8> = (0..4_500u32).map(|i| (i * 7 % 256) as u8).collect();
+
+    println!("📎 blob needs its document...");
+    assert!(db.put_blob(col, "u1", "avatar", "image/png", b"png").await.is_err());
+    db.add(col, "u1", &json!({ "name": "Ada" })).await?;
+
+    println!("📦 put, get and list...");
+    let info = db.put_blob(col, "u1", "cv", "application/pdf", &pdf).await?;
+    assert_eq!(info.size, 4_500);
+    assert_eq!(info.sha256.len(), 64);
+    let blob = db.get_blob(col, "u1", "cv").await?.unwrap();
+    assert_eq!(blob.info, info);
+    assert_eq!(blob.data, pdf);
+    db.put_blob(col, "u1", "avatar", "image/png", b"png").await?;
+    let names: Vec<String> = db.list_blobs(col, "u1").await?.into_iter().map(|b| b.name).collect();
+    assert_eq!(names, vec!["avatar", "cv"]);
+    // Blobs are not documents
+    assert_eq!(db.get(col, "u1").await?.unwrap(), json!({ "name": "Ada" }));
+
+    println!("🌊 streaming write and read...");
+    let mut writer = db.blob_writer(col, "u1", "cv", "application/pdf").await?;
+    for part in pdf.chunks(333).take(3) {
+        writer.write_all(part).await?;
+    }
+    writer.shutdown().await?;
+    let shorter = writer.commit().await?;
+    assert_eq!(shorter.size, 999);
+    let mut reader = db.blob_reader(col, "u1", "cv").await?.unwrap();
+    assert_eq!(reader.info(), &shorter);
+    let mut read = Vec::new();
+    reader.read_to_end(&mut read).await?;
+    assert_eq!(read, &pdf[..999]);
+    assert!(db.blob_reader(col, "u1", "missing").await?.is_none());
+
+    println!("🔒 transactional with the document...");
+    let mut tx = db.transaction().await?;
+    tx.add(col, "u2", &json!({ "name": "Bob" })).await?;
+    tx.put_blob(col, "u2", "avatar", "image/png", b"bob").await?;
+    tx.rollback().await?;
+    assert!(db.get_blob(col, "u2", "avatar").await?.is_none());
+    let mut tx = db.transaction().await?;
+    tx.add(col, "u2", &json!({ "name": "Bob" })).await?;
+    tx.put_blob(col, "u2", "avatar", "image/png", b"bob").await?;
+    tx.delete(col, "u2").await?;
+    tx.commit().await?;
+    assert!(db.list_blobs(col, "u2").await?.is_empty());
+
+    println!("🗑️ recursive delete takes blobs along...");
+    db.add(&format!("{}:u1:files", col), "f1", &json!({ "kind": "scan" })).await?;
+    db.put_blob(&format!("{}:u1:files", col), "f1", "page", "image/jpeg", &pdf).await?;
+    assert_eq!(db.delete_recursive(col, "u1").await?, 2);
+    assert!(db.list_blobs(col, "u1").await?.is_empty());
+    assert!(db.get_blob(&format!("{}:u1:files", col), "f1", "page").await?.is_none());
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn encrypted_blob_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?
+        .with_chunk_size(1000)
+        .with_key_provider(StaticKeys::new("k1", [5; 32]))
+        .with_collection("blob_secure", CollectionConfig::new().encryption(Cipher::Aes256Gcm));
+    let pdf: Vec<u8> = (0..4_500u32).map(|i| (i * 7 % 256) as u8).collect();
+
+    println!("🔐 encrypted blob chunks...");
+    let secure = "blob_secure";
+    db.add(secure, "s1", &json!({})).await?;
+    db.put_blob(secure, "s1", "key", "application/octet-stream", &pdf).await?;
+    assert_eq!(db.get_blob(secure, "s1", "key").await?.unwrap().data, pdf);
+
+    println!("🔄 rewrite re-seals blob chunks under the new key...");
+    let rotated = db.clone().with_key_provider(StaticKeys::new("k1", [5; 32]).rotate("k2", [6; 32]));
+    assert_eq!(rotated.rewrite(secure, 100).await?, 1);
+    assert_eq!(rotated.rewrite(secure, 100).await?, 0);
+    let k2_only = db.clone().with_key_provider(StaticKeys::new("k2", [6; 32]));
+    assert_eq!(k2_only.get_blob(secure, "s1", "key").await?.unwrap().data, pdf);
+    db.delete(secure, "s1").await?;
+    assert!(db.list_blobs(secure, "s1").await?.is_empty());
+    Ok(())
+}
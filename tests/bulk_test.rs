@@ -0,0 +1,51 @@
+use osunbitdb::{OsunbitDB, BulkOptions, json};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn bulk_write_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "bulk_test:u1";
+
+    println!("📦 Building 1000 docs...");
+    let mut docs = serde_json::Map::new();
+    for i in 0..1000 {
+        docs.insert(format!("e{:04}", i), json!({ "index": i, "payload": "x".repeat(100) }));
+    }
+
+    let reported = Arc::new(AtomicUsize::new(0));
+    let counter = reported.clone();
+    let opts = BulkOptions::new()
+        .max_keys(100)
+        .max_bytes(8 * 1024)
+        .concurrency(4)
+        .on_progress(move |p| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            println!("   🧩 chunk {}/{} keys={} bytes={}", p.chunk + 1, p.total_chunks, p.keys, p.bytes);
+        });
+
+    let summary = db.bulk_add(col, &json!(docs), &opts).await?;
+    println!("✅ {:?}", summary);
+    assert!(summary.is_complete());
+    assert_eq!(summary.written_keys, 1000);
+    // ~130 bytes per doc → byte limit splits before the key limit
+    assert!(summary.total_chunks > 10);
+    assert_eq!(reported.load(Ordering::SeqCst), summary.total_chunks);
+
+    let fetched = db.batch_get(col, &json!(["e0000", "e0999"])).await?;
+    assert_eq!(fetched["e0999"]["index"], 999);
+
+    println!("💥 a panicking chunk is reported, the others still finish...");
+    let opts = BulkOptions::new().max_keys(100).on_progress(|p| assert_ne!(p.chunk, 2, "boom"));
+    let ids: Vec<String> = docs.keys().cloned().collect();
+    let summary = db.bulk_delete(col, &json!(ids), &opts).await?;
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].chunk, 2);
+    assert_eq!(summary.failed[0].ids.len(), 100);
+    assert_eq!(summary.succeeded_chunks, summary.total_chunks - 1);
+
+    let summary = db.bulk_delete(col, &json!(ids), &BulkOptions::default()).await?;
+    assert!(summary.is_complete());
+    assert!(db.get(col, "e0500").await?.is_none());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use osunbitdb::{Filter, OsunbitDB, ScanOptions, StreamOptions, json};
+use futures::TryStreamExt;
+
+#[tokio::test]
+async fn chunking_test() -> Result<(), Box<dyn std::error::Error>> {
+    // Tiny chunks so a few KB already spans many keys
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?.with_chunk_size(1024);
+    let col = "chunk_test";
+
+    let big = json!({ "kind": "report", "body": (0..2_000).map(|i| i.to_string()).collect::<Vec<_>>() });
+    db.add(col, "big", &big).await?;
+    db.add(col, "small", &json!({ "kind": "note" })).await?;
+
+    println!("🧩 chunked value reassembled on every read path...");
+    assert_eq!(db.get(col, "big").await?.unwrap(), big);
+    let docs = db.batch_get(col, &json!(["big", "small"])).await?;
+    assert!(docs.is_complete());
+    assert_eq!(docs["big"], big);
+
+    let items = db.scan_with(col, &ScanOptions::new()).await?.items;
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].1, big);
+    let streamed: Vec<(String, serde_json::Value)> = db.stream(col, StreamOptions::new().page_size(1)).try_collect().await?;
+    assert_eq!(streamed[0].1, big);
+    let reports = ScanOptions::new().filter(Filter::eq("kind", "report"));
+    assert_eq!(db.count(col, &reports).await?, 1);
+
+    // Chunks are internal keys, never listed as collections
+    assert!(!db.list_collections("").await?.iter().any(|c| c.contains("chunk:")));
+
+    println!("✂️ overwrite and update keep chunks consistent...");
+    db.update(col, "big", json!({ "kind": "summary" })).await?;
+    assert_eq!(db.get(col, "big").await?.unwrap()["body"], big["body"]);
+    db.add(col, "big", &json!({ "kind": "shrunk" })).await?;
+    assert_eq!(db.get(col, "big").await?.unwrap(), json!({ "kind": "shrunk" }));
+    db.add(col, "big", &big).await?;
+    assert_eq!(db.get(col, "big").await?.unwrap(), big);
+
+    println!("🔁 one transaction shrinks and regrows a value it read...");
+    let mut tx = db.transaction().await?;
+    assert_eq!(tx.get(col, "big").await?.unwrap(), big);
+    tx.add(col, "big", &json!({ "kind": "shrunk" })).await?;
+    tx.add(col, "big", &big).await?;
+    tx.commit().await?;
+    assert_eq!(db.get(col, "big").await?.unwrap(), big);
+    db.batch_add(col, &json!({ "big": { "kind": "batch" } })).await?;
+    assert_eq!(db.get(col, "big").await?.unwrap(), json!({ "kind": "batch" }));
+    db.add(col, "big", &big).await?;
+
+    println!("🗑️ delete removes the chunks...");
+    db.batch_delete(col, &json!(["big", "small"])).await?;
+    assert!(db.get(col, "big").await?.is_none());
+    assert_eq!(db.count(col, &ScanOptions::new()).await?, 0);
+    Ok(())
+}
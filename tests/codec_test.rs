@@ -0,0 +1,52 @@
+use osunbitdb::{Codec, CollectionConfig, OsunbitDB, json};
+
+#[test]
+fn codec_roundtrip_test() {
+    let doc = json!({
+        "name": "Alice",
+        "age": 30,
+        "big": u64::MAX,
+        "score": 9.5,
+        "tags": ["a", "b"],
+        "profile": { "active": true, "bio": null }
+    });
+
+    for codec in [Codec::Json, Codec::MessagePack, Codec::Cbor, Codec::Bincode] {
+        let bytes = codec.encode(&doc).unwrap();
+        assert_eq!(bytes[0], codec.tag());
+        assert_eq!(Codec::decode(&bytes).unwrap(), doc, "{:?}", codec);
+    }
+
+    // Untagged values are legacy JSON
+    let legacy = serde_json::to_vec(&doc).unwrap();
+    assert_eq!(Codec::decode(&legacy).unwrap(), doc);
+
+    assert!(Codec::decode(&[Codec::Cbor.tag(), 0xff]).is_err());
+}
+
+#[tokio::test]
+async fn collection_codec_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let packed = db.clone().with_collection("codec_test", CollectionConfig::new().codec(Codec::MessagePack));
+
+    println!("📦 write with JSON, then MessagePack...");
+    db.add("codec_test", "old", &json!({ "n": 1 })).await?;
+    packed.add("codec_test", "new", &json!({ "n": 2 })).await?;
+
+    // Mixed data reads back either way
+    for client in [&db, &packed] {
+        let docs = client.batch_get("codec_test", &json!(["old", "new"])).await?;
+        assert!(docs.is_complete());
+        assert_eq!(docs["old"]["n"], 1);
+        assert_eq!(docs["new"]["n"], 2);
+        assert_eq!(client.scan_with("codec_test", &Default::default()).await?.len(), 2);
+    }
+
+    println!("🔁 rewrite to the configured codec...");
+    assert_eq!(packed.rewrite("codec_test", 1).await?, 1);
+    assert_eq!(packed.rewrite("codec_test", 100).await?, 0);
+    assert_eq!(db.get("codec_test", "old").await?.unwrap()["n"], 1);
+
+    db.batch_delete("codec_test", &json!(["old", "new"])).await?;
+    Ok(())
+}
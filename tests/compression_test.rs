@@ -0,0 +1,66 @@
+use osunbitdb::{CollectionConfig, Compression, OsunbitDB, OsunbitDBError, ScanOptions, MAX_DECOMPRESSED_BYTES, json};
+
+#[test]
+fn compression_roundtrip_test() {
+    let raw = serde_json::to_vec(&json!({ "body": "event ".repeat(200) })).unwrap();
+
+    for compression in [Compression::Lz4, Compression::Zstd(3)] {
+        let packed = compression.compress(raw.clone(), 256).unwrap();
+        assert!(packed.len() < raw.len(), "{:?}", compression);
+        assert_eq!(Compression::decompress(&packed).unwrap(), raw);
+    }
+
+    // Below the threshold or with no gain the value is left alone
+    let small = b"{\"a\":1}".to_vec();
+    assert_eq!(Compression::Zstd(3).compress(small.clone(), 256).unwrap(), small);
+    assert_eq!(Compression::decompress(&small).unwrap(), small);
+    assert_eq!(Compression::None.compress(raw.clone(), 0).unwrap(), raw);
+}
+
+#[test]
+fn decompression_bound_test() {
+    // An LZ4 size prefix past the cap is refused before anything is allocated
+    let mut forged = vec![0x10];
+    forged.extend(u32::MAX.to_le_bytes());
+    forged.extend(b"junk");
+    assert!(matches!(Compression::decompress(&forged), Err(OsunbitDBError::Corruption { .. })));
+
+    // A prefix that overstates the real size is caught too
+    let mut packed = Compression::Lz4.compress(vec![b'a'; 1_000], 0).unwrap();
+    packed[1..5].copy_from_slice(&2_000u32.to_le_bytes());
+    assert!(matches!(Compression::decompress(&packed), Err(OsunbitDBError::Corruption { .. })));
+
+    // zstd output stops at the cap
+    let bomb = Compression::Zstd(3).compress(vec![0; MAX_DECOMPRESSED_BYTES + 1], 0).unwrap();
+    assert!(matches!(Compression::decompress(&bomb), Err(OsunbitDBError::Corruption { .. })));
+    let truncated = &Compression::Zstd(3).compress(vec![b'a'; 1_000], 0).unwrap()[..6];
+    assert!(matches!(Compression::decompress(truncated), Err(OsunbitDBError::Corruption { .. })));
+}
+
+#[tokio::test]
+async fn collection_compression_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let zdb = db.clone().with_collection("compress_test", CollectionConfig::new().compression(Compression::Zstd(3), 256));
+
+    let big = json!({ "type": "click", "payload": "x".repeat(10_000) });
+    db.add("compress_test", "plain", &big).await?;
+    zdb.add("compress_test", "packed", &big).await?;
+    zdb.add("compress_test", "tiny", &json!({ "n": 1 })).await?;
+
+    println!("🗜️ compressed and plain values read back...");
+    let docs = zdb.batch_get("compress_test", &json!(["plain", "packed", "tiny"])).await?;
+    assert!(docs.is_complete());
+    assert_eq!(docs["packed"], big);
+    assert_eq!(db.get("compress_test", "packed").await?.unwrap(), big);
+
+    let stats = zdb.stats("compress_test").await?;
+    assert_eq!(stats.largest_id.as_deref(), Some("plain"));
+
+    println!("🔁 rewrite compresses the legacy value...");
+    assert_eq!(zdb.rewrite("compress_test", 100).await?, 1);
+    assert!(zdb.stats("compress_test").await?.total_bytes < stats.total_bytes);
+    assert_eq!(zdb.scan_with("compress_test", &ScanOptions::new()).await?.items[0].1, big);
+
+    db.batch_delete("compress_test", &json!(["plain", "packed", "tiny"])).await?;
+    Ok(())
+}
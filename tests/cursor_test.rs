@@ -0,0 +1,69 @@
+use osunbitdb::{OsunbitDB, json, Cursor, Direction, Filter, ScanOptions};
+
+#[test]
+fn cursor_token_test() {
+    let cursor = Cursor {
+        descending: false,
+        last_id: "inbox_010".to_string(),
+        collection_hash: 1,
+        filter_hash: 2,
+        snapshot_ts: 3,
+    };
+
+    // Unsigned round trip
+    let token = cursor.encode(None).unwrap();
+    assert!(!token.contains("inbox_010"));
+    assert_eq!(Cursor::decode(&token, None).unwrap(), cursor);
+    assert_eq!(cursor.direction(), Direction::Ascending);
+
+    // Signed round trip, wrong key and tampering are rejected
+    let signed = cursor.encode(Some(b"secret")).unwrap();
+    assert_eq!(Cursor::decode(&signed, Some(b"secret")).unwrap(), cursor);
+    assert!(Cursor::decode(&signed, Some(b"other")).is_err());
+    assert!(Cursor::decode(&signed, None).is_err());
+    assert!(Cursor::decode(&token, Some(b"secret")).is_err());
+
+    let mut bytes: Vec<char> = signed.chars().collect();
+    let i = bytes.len() / 2;
+    bytes[i] = if bytes[i] == 'A' { 'B' } else { 'A' };
+    let tampered: String = bytes.into_iter().collect();
+    assert!(Cursor::decode(&tampered, Some(b"secret")).is_err());
+    assert!(Cursor::decode("not a cursor!", None).is_err());
+}
+
+#[tokio::test]
+async fn cursor_pagination_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?.with_cursor_key(b"test-secret");
+    let col = "cursor_test:u1";
+
+    let mut docs = serde_json::Map::new();
+    for i in 1..=20 {
+        docs.insert(format!("m_{:03}", i), json!({ "index": i, "unread": i % 2 == 0 }));
+    }
+    db.batch_add(col, &json!(docs)).await?;
+
+    // Page through unread messages with opaque cursors
+    let filter = Filter::eq("unread", true);
+    let mut opts = ScanOptions::new().limit(3).filter(filter.clone());
+    let mut seen = Vec::new();
+    loop {
+        let page = db.scan_with(col, &opts).await?;
+        seen.extend(page.items.iter().map(|(_, d)| d["index"].as_i64().unwrap()));
+        match page.cursor {
+            Some(token) => opts = opts.cursor(&token),
+            None => break,
+        }
+    }
+    assert_eq!(seen, vec![2, 4, 6, 8, 10, 12, 14, 16, 18, 20]);
+
+    // A cursor cannot be reused with another filter or collection
+    let page = db.scan_with(col, &ScanOptions::new().limit(3).filter(filter)).await?;
+    let token = page.cursor.unwrap();
+    assert!(db.scan_with(col, &ScanOptions::new().limit(3).cursor(&token)).await.is_err());
+    assert!(db.scan_with("other:u1", &ScanOptions::new().limit(3)
+        .filter(Filter::eq("unread", true)).cursor(&token)).await.is_err());
+
+    let ids: Vec<String> = docs.keys().cloned().collect();
+    db.batch_delete(col, &json!(ids)).await?;
+    Ok(())
+}
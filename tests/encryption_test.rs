@@ -0,0 +1,68 @@
+use osunbitdb::{Cipher, CollectionConfig, KeyProvider, OsunbitDB, OsunbitDBError, StaticKeys, json};
+
+#[test]
+fn static_keys_rotation_test() {
+    let keys = StaticKeys::new("k1", [1; 32]).rotate("k2", [2; 32]).with_key("k0", [0; 32]);
+    assert_eq!(keys.current_key_id(), "k2");
+    assert_eq!(keys.key("k1"), Some([1; 32]));
+    assert_eq!(keys.key("k0"), Some([0; 32]));
+    assert!(keys.key("nope").is_none());
+    // Keys never show up in debug output
+    assert!(!format!("{:?}", keys).contains("[1"));
+}
+
+#[tokio::test]
+async fn encryption_test() -> Result<(), Box<dyn std::error::Error>> {
+    let plain = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let settings = CollectionConfig::new().encryption(Cipher::Aes256Gcm);
+    let v1 = plain.clone()
+        .with_key_provider(StaticKeys::new("k1", [7; 32]))
+        .with_collection("enc_test", settings.clone());
+
+    println!("🔐 write encrypted...");
+    v1.add("enc_test", "u1", &json!({ "ssn": "123-45-6789" })).await?;
+    v1.batch_add("enc_test", &json!({ "u2": { "ssn": "987-65-4321" } })).await?;
+    assert_eq!(v1.get("enc_test", "u1").await?.unwrap()["ssn"], "123-45-6789");
+
+    // Nothing readable without the key
+    let stats = plain.stats("enc_test").await?;
+    assert_eq!(stats.count, 2);
+    match plain.get("enc_test", "u1").await {
+        Err(OsunbitDBError::Encryption { .. }) => {}
+        other => panic!("expected encryption error, got {:?}", other),
+    }
+
+    println!("📋 a value copied onto another key does not open...");
+    let client = tikv_rust::TransactionClient::new(vec!["127.0.0.1:2379"]).await?;
+    let mut raw = client.begin_optimistic().await?;
+    let sealed = raw.get("enc_test:u1".to_string()).await?.unwrap();
+    raw.put("enc_test:u3".to_string(), sealed).await?;
+    raw.commit().await?;
+    assert!(matches!(v1.get("enc_test", "u3").await, Err(OsunbitDBError::Encryption { .. })));
+    v1.delete("enc_test", "u3").await?;
+
+    println!("🔁 rotate to k2 and re-encrypt...");
+    let v2 = plain.clone()
+        .with_key_provider(StaticKeys::new("k1", [7; 32]).rotate("k2", [9; 32]).with_key("k1", [7; 32]))
+        .with_collection("enc_test", settings.clone().encryption(Cipher::ChaCha20Poly1305));
+    assert_eq!(v2.get("enc_test", "u1").await?.unwrap()["ssn"], "123-45-6789");
+    assert_eq!(v2.rewrite("enc_test", 1).await?, 2);
+    assert_eq!(v2.rewrite("enc_test", 100).await?, 0);
+
+    // Values it cannot open are skipped instead of failing the run
+    let wrong_k2 = plain.clone()
+        .with_key_provider(StaticKeys::new("k2", [3; 32]))
+        .with_collection("enc_test", settings.clone().encryption(Cipher::ChaCha20Poly1305));
+    assert_eq!(wrong_k2.rewrite("enc_test", 100).await?, 0);
+
+    // k1 can be dropped once the job is done
+    let only_k2 = plain.clone()
+        .with_key_provider(StaticKeys::new("k2", [9; 32]))
+        .with_collection("enc_test", settings);
+    let docs = only_k2.batch_get("enc_test", &json!(["u1", "u2"])).await?;
+    assert!(docs.is_complete());
+    assert_eq!(docs["u2"]["ssn"], "987-65-4321");
+
+    only_k2.batch_delete("enc_test", &json!(["u1", "u2"])).await?;
+    Ok(())
+}
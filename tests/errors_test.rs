@@ -0,0 +1,63 @@
+use osunbitdb::{OsunbitDB, OsunbitDBError, json};
+use std::io;
+
+#[test]
+fn error_taxonomy_test() {
+    let unavailable = OsunbitDBError::from(tikv_rust::Error::NoCurrentRegions);
+    assert!(matches!(unavailable, OsunbitDBError::RegionUnavailable { context: None, .. }));
+    assert_eq!(unavailable.code(), "region_unavailable");
+    assert!(unavailable.is_retryable());
+
+    let timeout = OsunbitDBError::from(tikv_rust::Error::Io(io::Error::from(io::ErrorKind::TimedOut)));
+    assert_eq!(timeout.code(), "timeout");
+    assert!(timeout.is_retryable());
+
+    // A commit of unknown outcome must not be blindly retried
+    let undetermined = tikv_rust::Error::UndeterminedError(Box::new(tikv_rust::Error::NoCurrentRegions));
+    let undetermined = OsunbitDBError::from(undetermined);
+    assert_eq!(undetermined.code(), "tikv");
+    assert!(!undetermined.is_retryable());
+
+    let corrupt = OsunbitDBError::Corruption { reason: "bad header".to_string(), context: None };
+    assert_eq!(corrupt.code(), "corruption");
+    assert!(!corrupt.is_retryable());
+    assert_eq!(corrupt.to_string(), "Corrupt data: bad header");
+
+    let invalid = OsunbitDBError::InvalidId { id: "a:b".to_string(), reason: "no".to_string() };
+    assert_eq!(invalid.code(), "invalid_id");
+    assert!(invalid.context().is_none());
+}
+
+#[tokio::test]
+async fn errors_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "errors_test";
+
+    println!("🆕 create refuses to overwrite...");
+    db.create(col, "u1", &json!({ "name": "Ada" })).await?;
+    let err = db.create(col, "u1", &json!({ "name": "Bob" })).await.unwrap_err();
+    assert_eq!(err.code(), "already_exists");
+    assert!(!err.is_retryable());
+    assert!(matches!(&err, OsunbitDBError::AlreadyExists { id, .. } if id == "u1"));
+    assert_eq!(db.get(col, "u1").await?.unwrap()["name"], "Ada");
+
+    println!("🔍 missing parent document...");
+    let err = db.put_blob(col, "ghost", "avatar", "image/png", b"png").await.unwrap_err();
+    assert_eq!(err.code(), "not_found");
+
+    println!("🏷️ errors carry the call they came from...");
+    let context = err.context().unwrap();
+    assert_eq!(context.operation, "put_blob");
+    assert_eq!(context.collection.as_deref(), Some(col));
+    assert_eq!(context.id.as_deref(), Some("ghost"));
+    assert!(matches!(db.get(col, "bad:id").await, Err(OsunbitDBError::InvalidId { .. })));
+
+    let mut tx = db.transaction().await?;
+    let err = tx.create(col, "u1", &json!({})).await.unwrap_err();
+    tx.rollback().await?;
+    assert_eq!(err.context().unwrap().operation, "create");
+    assert_eq!(err.to_string(), "Document errors_test/u1 already exists (in create errors_test/u1)");
+
+    db.delete(col, "u1").await?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use osunbitdb::{CollectionConfig, FieldEncryption, Filter, OsunbitDB, OsunbitDBError, ScanOptions, StaticKeys, Update, json};
+
+#[tokio::test]
+async fn field_encryption_test() -> Result<(), Box<dyn std::error::Error>> {
+    let plain = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let db = plain.clone()
+        .with_key_provider(StaticKeys::new("k1", [3; 32]))
+        .with_collection("fenc_test", CollectionConfig::new()
+            .encrypt_field("ssn", FieldEncryption::Randomized)
+            .encrypt_field("contact.email", FieldEncryption::Deterministic));
+
+    println!("🔏 encrypted fields on add/batch_add/update...");
+    db.add("fenc_test", "u1", &json!({ "name": "Alice", "ssn": "111", "contact": { "email": "a@x.io" } })).await?;
+    db.batch_add("fenc_test", &json!({ "u2": { "name": "Bob", "ssn": "222", "contact": { "email": "a@x.io" } } })).await?;
+    db.update("fenc_test", "u2", Update::new().set("ssn", "333")).await?;
+
+    let u2 = db.get("fenc_test", "u2").await?.unwrap();
+    assert_eq!(u2["ssn"], "333");
+    assert_eq!(u2["contact"]["email"], "a@x.io");
+
+    println!("👀 without keys only the listed fields are hidden...");
+    let raw = plain.batch_get("fenc_test", &json!(["u1", "u2"])).await?;
+    assert_eq!(raw["u1"]["name"], "Alice");
+    assert!(raw["u1"]["ssn"].as_str().unwrap().starts_with("$enc:"));
+    assert_ne!(raw["u1"]["ssn"], raw["u2"]["ssn"]);
+
+    // Deterministic fields can be matched by their token
+    let token = db.field_token("fenc_test", "contact.email", &json!("a@x.io"))?;
+    assert_eq!(raw["u1"]["contact"]["email"], token);
+    let opts = ScanOptions::new().filter(Filter::eq("contact.email", token));
+    assert_eq!(plain.scan_with("fenc_test", &opts).await?.len(), 2);
+    assert!(db.field_token("fenc_test", "ssn", &json!("111")).is_err());
+
+    // With keys, filters see plaintext
+    let opts = ScanOptions::new().filter(Filter::eq("ssn", "111"));
+    assert_eq!(db.scan_with("fenc_test", &opts).await?.ids(), vec!["u1"]);
+
+    println!("🧵 strings that only look encrypted are user data...");
+    db.update("fenc_test", "u1", &json!({ "note": "$enc:AQA" })).await?;
+    db.add("fenc_plain_test", "u1", &json!({ "note": "$enc:AQA" })).await?;
+    assert_eq!(db.get("fenc_test", "u1").await?.unwrap()["note"], "$enc:AQA");
+    assert_eq!(db.get("fenc_plain_test", "u1").await?.unwrap()["note"], "$enc:AQA");
+    db.delete("fenc_plain_test", "u1").await?;
+
+    println!("🗄️ dropped rules stay readable only when retired...");
+    let retired = plain.clone()
+        .with_key_provider(StaticKeys::new("k1", [3; 32]))
+        .with_collection("fenc_test", CollectionConfig::new()
+            .encrypt_field("contact.email", FieldEncryption::Deterministic)
+            .retired_field("ssn"));
+    assert_eq!(retired.get("fenc_test", "u1").await?.unwrap()["ssn"], "111");
+    assert_eq!(retired.rewrite("fenc_test", 100).await?, 2);
+    assert_eq!(plain.get("fenc_test", "u1").await?.unwrap()["ssn"], "111");
+    let dropped = plain.clone().with_key_provider(StaticKeys::new("k1", [3; 32]));
+    assert!(dropped.get("fenc_test", "u2").await?.unwrap()["contact"]["email"].as_str().unwrap().starts_with("$enc:"));
+
+    println!("📋 a randomized field copied to another document does not open...");
+    plain.update("fenc_test", "u2", Update::new().set("ssn", raw["u1"]["ssn"].clone())).await?;
+    assert!(matches!(db.get("fenc_test", "u2").await, Err(OsunbitDBError::Encryption { .. })));
+
+    db.batch_delete("fenc_test", &json!(["u1", "u2"])).await?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use osunbitdb::{OsunbitDB, OsunbitDBError, json, CollectionGroup, Filter};
+
+#[tokio::test]
+async fn collection_group_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+
+    db.add("group_test:u1", "profile", &json!({ "unread": true })).await?;
+    db.batch_add("group_test:u1:inbox", &json!({
+        "m1": { "unread": true, "title": "a" },
+        "m2": { "unread": false, "title": "b" }
+    })).await?;
+    db.batch_add("group_test:u2:inbox", &json!({
+        "m1": { "unread": true, "title": "c" }
+    })).await?;
+    db.add("group_test:u2:outbox", "m9", &json!({ "unread": true })).await?;
+    db.put_blob("group_test:u1:inbox", "m1", "attachment", "text/plain", b"hello").await?;
+    let client = tikv_rust::TransactionClient::new(vec!["127.0.0.1:2379"]).await?;
+    let mut raw = client.begin_optimistic().await?;
+    raw.put("group_test:u3:inbox:bad".to_string(), b"\x01{not json".to_vec()).await?;
+    raw.commit().await?;
+
+    println!("📬 All unread inbox messages across users...");
+    let res = db.collection_group(&CollectionGroup::new("inbox")
+        .within("group_test")
+        .filter(Filter::eq("unread", true))).await?;
+    let paths: Vec<&str> = res.docs.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, vec!["group_test:u1:inbox:m1", "group_test:u2:inbox:m1"]);
+    assert_eq!(res.docs[1].collection, "group_test:u2:inbox");
+    assert_eq!(res.docs[1].id, "m1");
+    assert_eq!(res.docs[1].doc["title"], "c");
+    assert!(res.next.is_none());
+    // Undecodable documents are reported, not hidden as Null
+    assert_eq!(res.corrupt.len(), 1);
+    assert_eq!(res.corrupt[0].0, "group_test:u3:inbox:bad");
+
+    println!("📄 Paging with limit...");
+    let page = db.collection_group(&CollectionGroup::new("inbox").within("group_test").limit(2)).await?;
+    assert_eq!(page.docs.len(), 2);
+    let next = page.next.unwrap();
+    let page = db.collection_group(&CollectionGroup::new("inbox").within("group_test").limit(2).start_after(&next)).await?;
+    assert_eq!(page.docs.len(), 1);
+    assert_eq!(page.docs[0].path, "group_test:u2:inbox:m1");
+
+    println!("🌍 Whole keyspace skips blob and other internal keys...");
+    let res = db.collection_group(&CollectionGroup::new("inbox")).await?;
+    assert!(res.docs.iter().all(|d| !d.path.starts_with('\u{0}')));
+    let ours = res.docs.iter().filter(|d| d.path.starts_with("group_test:")).count();
+    assert_eq!(ours, 3);
+
+    db.delete("group_test:u1", "profile").await?;
+    db.delete_blob("group_test:u1:inbox", "m1", "attachment").await?;
+    db.batch_delete("group_test:u1:inbox", &json!(["m1", "m2"])).await?;
+    db.delete("group_test:u2:inbox", "m1").await?;
+    db.delete("group_test:u2:outbox", "m9").await?;
+    db.delete("group_test:u3:inbox", "bad").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn list_collections_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+
+    db.add("list_test", "u1", &json!({ "name": "Alice" })).await?;
+    db.batch_add("list_test:u1:inbox", &json!({ "m1": {}, "m2": {}, "m3": {} })).await?;
+    db.add("list_test:u1:outbox", "m1", &json!({})).await?;
+    db.add("list_test:u1:inbox:m1:replies", "r1", &json!({})).await?;
+    db.add("list_test:u2:settings", "s1", &json!({})).await?;
+
+    assert_eq!(db.list_collections("list_test:u1").await?, vec!["inbox", "outbox"]);
+    assert_eq!(db.list_collections("list_test:u1:inbox:m1").await?, vec!["replies"]);
+    assert_eq!(db.list_collections("list_test:u2").await?, vec!["settings"]);
+    assert!(db.list_collections("list_test:u3").await?.is_empty());
+    assert!(db.list_collections("").await?.contains(&"list_test".to_string()));
+    // u1 and u2 are documents with subcollections, not collections
+    assert!(matches!(db.list_collections("list_test").await, Err(OsunbitDBError::InvalidArgument { .. })));
+
+    db.delete("list_test", "u1").await?;
+    db.batch_delete("list_test:u1:inbox", &json!(["m1", "m2", "m3"])).await?;
+    db.delete("list_test:u1:outbox", "m1").await?;
+    db.delete("list_test:u1:inbox:m1:replies", "r1").await?;
+    db.delete("list_test:u2:settings", "s1").await?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use osunbitdb::{CollectionConfig, IdGenerator, OsunbitDB, json};
+
+#[test]
+fn id_generator_format_test() {
+    let id = IdGenerator::Random.generate().unwrap();
+    assert_eq!(id.len(), 20);
+    assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+    assert_ne!(id, IdGenerator::Random.generate().unwrap());
+
+    let a = IdGenerator::Ulid.generate().unwrap();
+    assert_eq!(a.len(), 26);
+
+    let u = IdGenerator::UuidV7.generate().unwrap();
+    assert_eq!(u.len(), 36);
+    assert_eq!(&u[14..15], "7");
+
+    assert_eq!(IdGenerator::custom(|| "fixed".to_string()).generate().as_deref(), Some("fixed"));
+    // Counter needs the transaction
+    assert!(IdGenerator::Counter.generate().is_none());
+}
+
+#[tokio::test]
+async fn add_auto_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?
+        .with_collection("ids_test:*:orders", CollectionConfig::new().id_generator(IdGenerator::Counter));
+
+    println!("🆔 random id...");
+    let id = db.add_auto("ids_test", &json!({ "name": "Alice" })).await?;
+    assert_eq!(id.len(), 20);
+    assert_eq!(db.get("ids_test", &id).await?.unwrap()["name"], "Alice");
+    db.delete("ids_test", &id).await?;
+
+    println!("🔢 counter ids...");
+    let col = format!("ids_test:{}:orders", IdGenerator::Random.generate().unwrap());
+    let first = db.add_auto(&col, &json!({ "n": 1 })).await?;
+    let mut tx = db.transaction().await?;
+    let second = tx.add_auto(&col, &json!({ "n": 2 })).await?;
+    let third = tx.add_auto(&col, &json!({ "n": 3 })).await?;
+    tx.commit().await?;
+    assert_eq!(first, "00000000000000000001");
+    assert_eq!(second, "00000000000000000002");
+    assert_eq!(third, "00000000000000000003");
+
+    println!("🧹 counter is not a collection...");
+    assert!(!db.list_collections("").await?.iter().any(|c| c.starts_with('\0')));
+
+    db.batch_delete(&col, &json!([first, second, third])).await?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use osunbitdb::{CollectionConfig, OsunbitDB, json};
+
+/// v1 `{name}` → v2 `{first, last}` → v3 adds `active`.
+fn versioned() -> CollectionConfig {
+    CollectionConfig::new()
+        .migration(|mut doc| {
+            let name = doc["name"].as_str().unwrap_or_default().to_string();
+            let (first, last) = name.split_once(' ').unwrap_or((name.as_str(), ""));
+            doc["first"] = json!(first);
+            doc["last"] = json!(last);
+            doc.as_object_mut().map(|o| o.remove("name"));
+            Ok(doc)
+        })
+        .migration(|mut doc| {
+            doc["active"] = json!(true);
+            Ok(doc)
+        })
+}
+
+#[test]
+fn schema_version_test() {
+    assert_eq!(CollectionConfig::new().schema_version(), 1);
+    assert_eq!(versioned().schema_version(), 3);
+}
+
+#[tokio::test]
+async fn migration_test() -> Result<(), Box<dyn std::error::Error>> {
+    let v1 = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let v3 = v1.clone().with_collection("migrate_test", versioned());
+
+    let mut docs = serde_json::Map::new();
+    for i in 0..25 {
+        docs.insert(format!("u{:02}", i), json!({ "name": format!("User {}", i) }));
+    }
+    v1.batch_add("migrate_test", &json!(docs)).await?;
+
+    println!("🧬 lazy migration on read...");
+    let doc = v3.get("migrate_test", "u01").await?.unwrap();
+    assert_eq!(doc, json!({ "first": "User", "last": "1", "active": true }));
+    // Still stored as v1 until written
+    assert_eq!(v1.get("migrate_test", "u01").await?.unwrap()["name"], "User 1");
+
+    println!("✍️ persisted on next write...");
+    v3.update("migrate_test", "u01", &json!({ "last": "One" })).await?;
+    assert_eq!(v3.get("migrate_test", "u01").await?.unwrap()["last"], "One");
+
+    println!("🏃 eager runner...");
+    assert_eq!(v3.migrate("migrate_test", 10).await?, 24);
+    assert_eq!(v3.migrate("migrate_test", 10).await?, 0);
+    let all = v3.scan_with("migrate_test", &Default::default()).await?;
+    assert!(all.items.iter().all(|(_, d)| d["active"] == true && d.get("name").is_none()));
+
+    let ids: Vec<String> = docs.keys().cloned().collect();
+    v1.batch_delete("migrate_test", &json!(ids)).await?;
+    Ok(())
+}
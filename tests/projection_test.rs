@@ -0,0 +1,55 @@
+use osunbitdb::{OsunbitDB, json, FieldMask, Filter, ScanOptions};
+
+#[test]
+fn field_mask_apply_test() {
+    let doc = json!({
+        "name": "Alice",
+        "bio": "long text...",
+        "profile": { "avatar": "a.png", "banner": "b.png" },
+        "items": [ { "sku": "s1", "qty": 1 }, { "sku": "s2", "qty": 2 }, { "note": "x" } ],
+        "stats": { "2024": { "views": 10, "likes": 1 }, "2025": { "views": 20, "likes": 2 } }
+    });
+
+    let mask = FieldMask::new(["name", "profile.avatar", "items.*.sku", "stats.*.views", "missing.path"]);
+    assert_eq!(mask.apply(&doc), json!({
+        "name": "Alice",
+        "profile": { "avatar": "a.png" },
+        "items": [ { "sku": "s1" }, { "sku": "s2" } ],
+        "stats": { "2024": { "views": 10 }, "2025": { "views": 20 } }
+    }));
+
+    // Exact and wildcard paths on the same level are merged
+    let mask = FieldMask::parse("stats.*.views, stats.2024.likes");
+    assert_eq!(mask.apply(&doc)["stats"]["2024"], json!({ "views": 10, "likes": 1 }));
+
+    // Empty mask keeps everything, nothing matched gives an empty object
+    assert_eq!(FieldMask::parse("").apply(&doc), doc);
+    assert_eq!(FieldMask::new(["nope"]).apply(&doc), json!({}));
+}
+
+#[tokio::test]
+async fn masked_reads_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "projection_test";
+
+    db.batch_add(col, &json!({
+        "u1": { "name": "Alice", "age": 30, "profile": { "avatar": "a.png", "bio": "..." } },
+        "u2": { "name": "Bob", "age": 20, "profile": { "avatar": "b.png", "bio": "..." } }
+    })).await?;
+    let mask = FieldMask::new(["name", "profile.avatar"]);
+
+    let doc = db.get_masked(col, "u1", &mask).await?.unwrap();
+    assert_eq!(doc, json!({ "name": "Alice", "profile": { "avatar": "a.png" } }));
+
+    let docs = db.batch_get_masked(col, &json!(["u1", "u2"]), &mask).await?;
+    assert!(docs["u2"].get("age").is_none());
+
+    // The filter sees the full document, the mask trims the result
+    let page = db.scan_with(col, &ScanOptions::new()
+        .filter(Filter::gt("age", 25))
+        .mask(mask)).await?;
+    assert_eq!(page.items, vec![("u1".to_string(), json!({ "name": "Alice", "profile": { "avatar": "a.png" } }))]);
+
+    db.batch_delete(col, &json!(["u1", "u2"])).await?;
+    Ok(())
+}
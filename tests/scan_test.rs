@@ -1,4 +1,5 @@
-use osunbitdb::{OsunbitDB, json};
+use osunbitdb::{OsunbitDB, json, Consistency, Direction, ScanOptions, StreamOptions};
+use futures::TryStreamExt;
 use tokio::time::{sleep, Duration};
 
 #[tokio::test]
@@ -200,3 +201,95 @@ async fn scan_test_straightforward() -> Result<(), Box<dyn std::error::Error>> {
     println!("🎯 Test complete — full scan flow verified.\n============================================================");
     Ok(())
 }
+
+#[tokio::test]
+async fn stream_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "stream_test:u1";
+
+    let mut docs = serde_json::Map::new();
+    for i in 1..=25 {
+        docs.insert(format!("doc_{:03}", i), json!({ "index": i }));
+    }
+    db.batch_add(col, &json!(docs)).await?;
+    // A subcollection document inside the collection's key range
+    db.add("stream_test:u1:doc_005:notes", "n1", &json!({ "index": 99 })).await?;
+
+    // 🔼 Whole collection, 4 docs per page
+    let all: Vec<(String, serde_json::Value)> = db
+        .stream(col, StreamOptions::new().page_size(4))
+        .try_collect()
+        .await?;
+    assert_eq!(all.len(), 25);
+    assert_eq!(all[0].0, "doc_001");
+    assert_eq!(all[24].1["index"], 25);
+
+    // 🔽 Descending between bounds (start inclusive, end exclusive), one snapshot
+    let opts = StreamOptions::new()
+        .page_size(3)
+        .direction(Direction::Descending)
+        .start("doc_010")
+        .end("doc_020")
+        .consistency(Consistency::Snapshot);
+    let ids: Vec<String> = db
+        .stream(col, opts)
+        .map_ok(|(id, _)| id)
+        .try_collect()
+        .await?;
+    assert_eq!(ids.len(), 10);
+    assert_eq!(ids.first().unwrap(), "doc_019");
+    assert_eq!(ids.last().unwrap(), "doc_010");
+
+    let ids: Vec<String> = docs.keys().cloned().collect();
+    db.batch_delete(col, &json!(ids)).await?;
+    db.delete("stream_test:u1:doc_005:notes", "n1").await?;
+    Ok(())
+}
+
+#[test]
+fn scan_options_validation_test() {
+    assert_eq!(Direction::parse("a").unwrap(), Direction::Ascending);
+    assert_eq!(Direction::parse("DESC").unwrap(), Direction::Descending);
+    assert!(Direction::parse("x").is_err());
+
+    assert!(ScanOptions::new().validate().is_ok());
+    assert!(ScanOptions::new().limit(0).validate().is_err());
+    assert!(ScanOptions::new().start_at("b").end_before("a").validate().is_err());
+    assert!(ScanOptions::new().start_at("a").end_before("a").validate().is_err());
+    assert!(ScanOptions::new().start_at("a").end_at("a").validate().is_ok());
+}
+
+#[tokio::test]
+async fn scan_with_options_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "scan_opts_test:u1";
+
+    db.batch_add(col, &json!({
+        "a_1": { "n": 1 }, "a_2": { "n": 2 }, "a_3": { "n": 3 },
+        "b_1": { "n": 4 }, "b_2": { "n": 5 }
+    })).await?;
+
+    // Inclusive start, inclusive end
+    let res = db.scan_with(col, &ScanOptions::new().start_at("a_2").end_at("b_1")).await?;
+    assert_eq!(res.ids(), vec!["a_2", "a_3", "b_1"]);
+
+    // Prefix + descending + keys only
+    let res = db.scan_with(col, &ScanOptions::new()
+        .prefix("a_")
+        .direction(Direction::Descending)
+        .keys_only(true)).await?;
+    assert_eq!(res.ids(), vec!["a_3", "a_2", "a_1"]);
+    assert!(res.items[0].1.is_null());
+
+    // Exclusive start with limit, then continue from last_id
+    let page = db.scan_with(col, &ScanOptions::new().start_after("a_1").limit(2)).await?;
+    assert_eq!(page.ids(), vec!["a_2", "a_3"]);
+    let next = db.scan_with(col, &ScanOptions::new().start_after(page.last_id().unwrap()).limit(2)).await?;
+    assert_eq!(next.ids(), vec!["b_1", "b_2"]);
+
+    // Invalid order is rejected instead of silently scanning ascending
+    assert!(db.scan(col, 10, "", "up").await.is_err());
+
+    db.batch_delete(col, &json!(["a_1", "a_2", "a_3", "b_1", "b_2"])).await?;
+    Ok(())
+}
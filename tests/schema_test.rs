@@ -0,0 +1,104 @@
+use osunbitdb::{CollectionConfig, Limits, OsunbitDB, OsunbitDBError, Schema, Update, json};
+
+fn user_schema() -> Schema {
+    Schema::new(json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "required": ["name", "age"],
+        "properties": {
+            "name": { "type": "string", "minLength": 1 },
+            "age": { "type": "integer", "minimum": 0 },
+            "email": { "type": "string", "pattern": "^[^@]+@[^@]+$" },
+            "role": { "enum": ["admin", "member"] },
+            "tags": { "type": "array", "items": { "type": "string" }, "uniqueItems": true },
+            "address": {
+                "type": "object",
+                "properties": { "zip": { "type": "string", "maxLength": 5 } },
+                "additionalProperties": false
+            }
+        }
+    })).unwrap()
+}
+
+#[test]
+fn schema_validate_test() {
+    let schema = user_schema();
+    assert!(schema.is_valid(&json!({ "name": "Alice", "age": 30, "tags": ["a", "b"] })));
+
+    let violations = schema.validate(&json!({
+        "age": -1.5,
+        "email": "nope",
+        "role": "root",
+        "tags": ["a", 1, "a"],
+        "address": { "zip": "1234567", "city": "Oslo" }
+    }));
+    let mut paths: Vec<&str> = violations.iter().map(|v| v.path.as_str()).collect();
+    paths.sort_unstable();
+    assert_eq!(paths, ["/address/city", "/address/zip", "/age", "/email", "/name", "/role", "/tags", "/tags/1"]);
+    let age = violations.iter().find(|v| v.path == "/age").unwrap();
+    assert_eq!(age.message, "expected integer, got number");
+
+    // Combinators and boolean schemas
+    let one = Schema::new(json!({ "oneOf": [{ "type": "string" }, { "type": "integer" }], "not": { "const": 0 } })).unwrap();
+    assert!(one.is_valid(&json!("x")));
+    assert!(!one.is_valid(&json!(0)));
+    assert!(!one.is_valid(&json!(1.5)));
+    assert!(!Schema::new(json!(false)).unwrap().is_valid(&json!({})));
+
+    // Unsupported or malformed schemas are rejected up front
+    assert!(Schema::new(json!({ "$ref": "#/$defs/x" })).is_err());
+    assert!(Schema::new(json!({ "type": "str" })).is_err());
+    assert!(Schema::new(json!({ "pattern": "(" })).is_err());
+}
+
+#[tokio::test]
+async fn schema_writes_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?
+        .with_collection("schema_test", CollectionConfig::new().schema(user_schema()));
+
+    println!("✅ valid add...");
+    db.add("schema_test", "u1", &json!({ "name": "Alice", "age": 30 })).await?;
+
+    println!("🚫 invalid add...");
+    match db.add("schema_test", "u2", &json!({ "name": "" })).await {
+        Err(OsunbitDBError::Validation(v)) => {
+            assert_eq!(v.len(), 2);
+            assert!(v.iter().all(|v| v.id == "u2"));
+        }
+        other => panic!("expected validation error, got {:?}", other),
+    }
+    assert!(db.get("schema_test", "u2").await?.is_none());
+
+    println!("🚫 update checked on the merged document...");
+    assert!(matches!(
+        db.update("schema_test", "u1", Update::new().remove("age")).await,
+        Err(OsunbitDBError::Validation(_))
+    ));
+    db.update("schema_test", "u1", Update::new().inc("age", 1)).await?;
+    assert_eq!(db.get("schema_test", "u1").await?.unwrap()["age"], 31);
+
+    println!("🚫 batch_add reports every document and writes none...");
+    let res = db.batch_add("schema_test", &json!({
+        "b1": { "name": "Bob", "age": 1 },
+        "b2": { "name": "Carl" },
+        "b3": { "name": "Dan", "age": "old" }
+    })).await;
+    match res {
+        Err(OsunbitDBError::Validation(v)) => {
+            let ids: Vec<&str> = v.iter().map(|v| v.id.as_str()).collect();
+            assert_eq!(ids, vec!["b2", "b3"]);
+        }
+        other => panic!("expected validation error, got {:?}", other),
+    }
+    assert!(db.get("schema_test", "b1").await?.is_none());
+
+    println!("📏 limits are checked before the schema...");
+    let deep = db.clone().with_limits(Limits::new().max_depth(2));
+    assert!(matches!(
+        deep.add("schema_test", "d1", &json!({ "name": "", "address": { "zip": { "code": 1 } } })).await,
+        Err(OsunbitDBError::DocumentTooDeep { max: 2, .. })
+    ));
+
+    db.delete("schema_test", "u1").await?;
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use osunbitdb::{OsunbitDB, json, Filter, ScanOptions};
+
+#[tokio::test]
+async fn count_exists_stats_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+    let col = "stats_test:u1";
+
+    let mut docs = serde_json::Map::new();
+    for i in 1..=30 {
+        docs.insert(format!("inbox_{:03}", i), json!({ "index": i, "unread": i <= 10 }));
+    }
+    docs.insert("inbox_big".to_string(), json!({ "index": 0, "body": "x".repeat(1000) }));
+    db.batch_add(col, &json!(docs)).await?;
+    // Same key range, but a subcollection: never counted as col's
+    let replies = format!("{}:inbox_big:replies", col);
+    db.add(&replies, "r9", &json!({ "unread": true, "body": "y".repeat(5000) })).await?;
+
+    println!("🔎 exists...");
+    assert!(db.exists(col, "inbox_001").await?);
+    assert!(!db.exists(col, "inbox_999").await?);
+
+    println!("🔢 count...");
+    assert_eq!(db.count(col, &ScanOptions::new()).await?, 31);
+    assert_eq!(db.count(col, &ScanOptions::new().start_at("inbox_011").end_at("inbox_020")).await?, 10);
+    assert_eq!(db.count(col, &ScanOptions::new().filter(Filter::eq("unread", true))).await?, 10);
+
+    println!("📋 listings agree with count...");
+    let all = ScanOptions::new().limit(100);
+    assert_eq!(db.scan_with(col, &all).await?.len() as u64, db.count(col, &ScanOptions::new()).await?);
+    let unread = ScanOptions::new().limit(100).filter(Filter::eq("unread", true));
+    assert_eq!(db.scan_with(col, &unread).await?.len() as u64, db.count(col, &unread).await?);
+
+    println!("📊 stats...");
+    let stats = db.stats(col).await?;
+    println!("{:?}", stats);
+    assert_eq!(stats.count, 31);
+    assert_eq!(stats.min_id.as_deref(), Some("inbox_001"));
+    assert_eq!(stats.max_id.as_deref(), Some("inbox_big"));
+    assert_eq!(stats.largest_id.as_deref(), Some("inbox_big"));
+    assert!(stats.largest_bytes > 1000);
+    assert!(stats.total_bytes > stats.largest_bytes);
+
+    // Same helpers inside a transaction see uncommitted writes
+    let mut tx = db.transaction().await?;
+    tx.add(col, "inbox_new", &json!({ "index": 99 })).await?;
+    assert!(tx.exists(col, "inbox_new").await?);
+    assert_eq!(tx.count(col, &ScanOptions::new()).await?, 32);
+    tx.rollback().await?;
+
+    let ids: Vec<String> = docs.keys().cloned().collect();
+    db.batch_delete(col, &json!(ids)).await?;
+    db.delete(&replies, "r9").await?;
+    assert_eq!(db.count(col, &ScanOptions::new()).await?, 0);
+    Ok(())
+}
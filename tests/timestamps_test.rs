@@ -0,0 +1,53 @@
+use osunbitdb::{CollectionConfig, OsunbitDB, OsunbitDBError, json, server_timestamp, Update, CREATED_AT, UPDATED_AT};
+
+#[tokio::test]
+async fn timestamps_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?
+        .with_collection("ts_test", CollectionConfig::new().timestamps(true));
+
+    println!("🕒 createdAt on first write...");
+    db.add("ts_test", "a", &json!({ "name": "Alice" })).await?;
+    let first = db.get("ts_test", "a").await?.unwrap();
+    let created = first[CREATED_AT].as_i64().unwrap();
+    assert_eq!(first[UPDATED_AT], created);
+
+    println!("🔁 updatedAt on every write, createdAt kept...");
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    db.update("ts_test", "a", Update::new().set("name", "Alicia")).await?;
+    let doc = db.get("ts_test", "a").await?.unwrap();
+    assert_eq!(doc[CREATED_AT], created);
+    assert!(doc[UPDATED_AT].as_i64().unwrap() > created);
+
+    // A full replace keeps createdAt too
+    db.add("ts_test", "a", &json!({ "name": "Al" })).await?;
+    assert_eq!(db.get("ts_test", "a").await?.unwrap()[CREATED_AT], created);
+
+    println!("🤝 one timestamp per transaction...");
+    let mut tx = db.transaction().await?;
+    let now = tx.write_time();
+    tx.batch_add("ts_test", &json!({ "b": { "n": 1 }, "c": { "n": 2 } })).await?;
+    tx.update("ts_test", "a", &json!({ "n": 3 })).await?;
+    tx.commit().await?;
+    let docs = db.batch_get("ts_test", &json!(["a", "b", "c"])).await?;
+    for id in ["a", "b", "c"] {
+        assert_eq!(docs[id][UPDATED_AT], now);
+    }
+    assert_eq!(docs["b"][CREATED_AT], now);
+
+    println!("⏱️ server_timestamp() op without the collection setting...");
+    db.add("ts_plain", "x", &json!({ "at": server_timestamp() })).await?;
+    db.update("ts_plain", "x", Update::new().server_timestamp("seen.at")).await?;
+    let doc = db.get("ts_plain", "x").await?.unwrap();
+    assert!(doc["at"].is_i64());
+    assert!(doc["seen"]["at"].is_i64());
+    // A placeholder inside a builder set is refused, it would not survive a read and re-add
+    for up in [Update::new().set("raw", server_timestamp()), Update::new().set("raw", json!({ "at": server_timestamp() }))] {
+        assert!(matches!(db.update("ts_plain", "x", up).await, Err(OsunbitDBError::InvalidUpdate { .. })));
+    }
+    assert!(db.get("ts_plain", "x").await?.unwrap().get("raw").is_none());
+    assert!(doc.get(CREATED_AT).is_none());
+
+    db.batch_delete("ts_test", &json!(["a", "b", "c"])).await?;
+    db.delete("ts_plain", "x").await?;
+    Ok(())
+}
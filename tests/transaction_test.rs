@@ -73,3 +73,34 @@ async fn transaction_atomic_ops_test() {
     db.delete("users", "u1").await.unwrap();
     db.delete("notifications:u1", "n1").await.unwrap();
 }
+
+#[tokio::test]
+async fn returning_writes_test() {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await.unwrap();
+
+    // add_returning on a fresh id has no "before"
+    let res = db.add_returning("users", "ret1", &json!({"balance": 100})).await.unwrap();
+    assert!(res.before.is_none());
+    assert_eq!(res.after.unwrap()["balance"], 100);
+
+    // update_returning gives both states without a second get
+    let res = db.update_returning("users", "ret1", &json!({
+        "balance": increment(25)
+    })).await.unwrap();
+    assert_eq!(res.before.unwrap()["balance"], 100);
+    assert_eq!(res.after.unwrap()["balance"], 125);
+
+    // Inside a transaction
+    let mut tx = db.transaction().await.unwrap();
+    let res = tx.update_returning("users", "ret1", &json!({
+        "balance": increment(-5)
+    })).await.unwrap();
+    assert_eq!(res.after.unwrap()["balance"], 120);
+    tx.commit().await.unwrap();
+
+    // delete_returning gives back the deleted document
+    let res = db.delete_returning("users", "ret1").await.unwrap();
+    assert_eq!(res.before.unwrap()["balance"], 120);
+    assert!(res.after.is_none());
+    assert!(db.get("users", "ret1").await.unwrap().is_none());
+}
@@ -0,0 +1,70 @@
+use osunbitdb::{OsunbitDB, OsunbitDBError, json, increment, remove, server_timestamp, Update, FieldOp, IntoUpdate};
+
+#[test]
+fn update_from_json_test() {
+    let up = Update::from_json(&json!({
+        "name": "Bob",
+        "balance": increment(5),
+        "role": remove(),
+        "seenAt": server_timestamp()
+    })).unwrap();
+
+    assert_eq!(up.ops().len(), 4);
+    assert!(up.ops().contains(&("seenAt".to_string(), FieldOp::ServerTimestamp)));
+    assert!(up.ops().contains(&("name".to_string(), FieldOp::Set(json!("Bob")))));
+    assert!(up.ops().contains(&("balance".to_string(), FieldOp::Increment(json!(5)))));
+    assert!(up.ops().contains(&("role".to_string(), FieldOp::Remove)));
+
+    // Placeholders nested in a json! set value become their own ops
+    let up = Update::from_json(&json!({ "profile": { "name": "Bob", "editedAt": server_timestamp() } })).unwrap();
+    assert_eq!(up.ops(), &[
+        ("profile".to_string(), FieldOp::Set(json!({ "name": "Bob" }))),
+        ("profile.editedAt".to_string(), FieldOp::ServerTimestamp),
+    ]);
+
+    // Non-object fields are rejected
+    assert!(Update::from_json(&json!(["nope"])).is_err());
+
+    // So are non-numeric increments, in either form
+    assert!(matches!(Update::from_json(&json!({ "n": increment("5") })), Err(OsunbitDBError::InvalidUpdate { .. })));
+    assert!(matches!(Update::new().inc("n", true).into_update(), Err(OsunbitDBError::InvalidUpdate { .. })));
+}
+
+#[tokio::test]
+async fn update_builder_test() -> Result<(), Box<dyn std::error::Error>> {
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?;
+
+    db.add("users", "ub1", &json!({"balance": 100, "role": "admin", "tags": ["db"]})).await?;
+
+    // ✏️ Typed builder
+    let up = Update::new()
+        .inc("balance", 25)
+        .remove("role")
+        .array_union("tags", ["rust", "db"])
+        .set("profile.name", "Alice");
+    db.update("users", "ub1", up).await?;
+
+    let doc = db.get("users", "ub1").await?.unwrap();
+    assert_eq!(doc["balance"], 125);
+    assert!(doc.get("role").is_none());
+    assert_eq!(doc["tags"], json!(["db", "rust"]));
+    assert_eq!(doc["profile"]["name"], "Alice");
+
+    // 🧩 A real field named "__op" is stored as-is with `set`
+    db.update("users", "ub1", Update::new().set("meta", json!({"__op": "inc", "amount": 1}))).await?;
+    let doc = db.get("users", "ub1").await?.unwrap();
+    assert_eq!(doc["meta"]["__op"], "inc");
+
+    // json! form keeps working
+    db.update("users", "ub1", &json!({"balance": increment(-25)})).await?;
+    let doc = db.get("users", "ub1").await?.unwrap();
+    assert_eq!(doc["balance"], 100);
+
+    // 🔢 An i64 overflow falls back to f64 instead of panicking
+    db.update("users", "ub1", Update::new().set("big", i64::MAX).inc("big", 1)).await?;
+    let doc = db.get("users", "ub1").await?.unwrap();
+    assert_eq!(doc["big"], json!(i64::MAX as f64 + 1.0));
+
+    db.delete("users", "ub1").await?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use osunbitdb::{Limits, OsunbitDB, OsunbitDBError, ScanOptions, json};
+
+#[test]
+fn limits_builder_test() {
+    #[cfg(feature = "schema")]
+    assert!(Limits::new().id_pattern("[A-Za-z0-9_-]+").is_ok());
+    assert!(matches!(Limits::new().id_pattern("[unclosed"), Err(OsunbitDBError::InvalidArgument { .. })));
+
+    let err = OsunbitDBError::InvalidId { id: "a:b".to_string(), reason: "must not contain the ':' path separator".to_string() };
+    assert_eq!(err.to_string(), "Invalid document id \"a:b\": must not contain the ':' path separator");
+    assert_eq!(OsunbitDBError::DocumentTooLarge { size: 20, max: 10, context: None }.to_string(), "Document is 20 bytes, the limit is 10");
+}
+
+#[tokio::test]
+async fn validation_test() -> Result<(), Box<dyn std::error::Error>> {
+    let limits = Limits::new()
+        .max_document_bytes(1_000)
+        .max_depth(3)
+        .max_id_length(16)
+        .reserve("__*")
+        .reserve("admin");
+    #[cfg(feature = "schema")]
+    let limits = limits.id_pattern("[a-z0-9_]+")?;
+    let db = OsunbitDB::new(&["http://127.0.0.1:2379"]).await?.with_limits(limits);
+    let col = "validation_test";
+
+    println!("🚫 bad collection names...");
+    assert!(matches!(db.get("", "u1").await, Err(OsunbitDBError::InvalidCollection { .. })));
+    assert!(matches!(db.add("users::inbox", "m1", &json!({})).await, Err(OsunbitDBError::InvalidCollection { .. })));
+    assert!(matches!(db.count("a\u{0}b", &ScanOptions::new()).await, Err(OsunbitDBError::InvalidCollection { .. })));
+    assert!(matches!(db.list_collections("__meta").await, Err(OsunbitDBError::ReservedName(_))));
+
+    println!("🚫 bad ids...");
+    assert!(matches!(db.add(col, "u1:inbox", &json!({})).await, Err(OsunbitDBError::InvalidId { .. })));
+    assert!(matches!(db.get(col, "").await, Err(OsunbitDBError::InvalidId { .. })));
+    #[cfg(feature = "schema")]
+    assert!(matches!(db.get(col, "UPPER").await, Err(OsunbitDBError::InvalidId { .. })));
+    assert!(matches!(db.get(col, "a_very_long_id_indeed").await, Err(OsunbitDBError::InvalidId { .. })));
+    assert!(matches!(db.delete(col, "admin").await, Err(OsunbitDBError::ReservedName(_))));
+    // batch_get reports bad ids instead of failing the call
+    assert_eq!(db.batch_get(col, &json!(["ok", "no:pe"])).await?.invalid, vec![json!("no:pe")]);
+    assert!(matches!(db.batch_update(col, &json!({ "no:pe": { "a": 1 } })).await, Err(OsunbitDBError::InvalidId { .. })));
+
+    println!("🚫 oversized and deep documents...");
+    let big = json!({ "body": "x".repeat(2_000) });
+    assert!(matches!(db.add(col, "big", &big).await, Err(OsunbitDBError::DocumentTooLarge { max: 1_000, .. })));
+    let deep = json!({ "a": { "b": { "c": { "d": 1 } } } });
+    assert!(matches!(db.add(col, "deep", &deep).await, Err(OsunbitDBError::DocumentTooDeep { max: 3, .. })));
+    // Batches say which item failed
+    let err = db.batch_add(col, &json!({ "fine": {}, "big": big })).await.unwrap_err();
+    let context = err.context().unwrap();
+    assert_eq!((context.operation, context.id.as_deref()), ("batch_add", Some("big")));
+    db.add(col, "ok", &json!({ "a": { "b": { "c": 1 } } })).await?;
+    // Updates are checked on the merged document
+    assert!(matches!(
+        db.update(col, "ok", json!({ "body": "x".repeat(2_000) })).await,
+        Err(OsunbitDBError::DocumentTooLarge { .. })
+    ));
+    assert!(db.get(col, "ok").await?.unwrap().get("body").is_none());
+
+    db.delete(col, "ok").await?;
+    Ok(())
+}